@@ -0,0 +1,77 @@
+//! Compares the bytecode VM against the tree-walker
+//! 
+//! ```
+//! cargo bench --bench vm
+//! ```
+
+use std::time::{Duration, Instant};
+
+use jester::{Env, Err};
+
+/// Definitions shared by every case
+const SETUP: &str = "
+(defun fib (n) 
+    (if (< n 2) 
+        n 
+        (+ (fib (- n 1)) (fib (- n 2)))))
+
+(defun sum-to (n)
+    (let (acc 0)
+        (for i in 0 to n
+            (set acc (+ acc i)))
+        acc))
+
+(defun count-down (n acc)
+    (if (= n 0) 
+        acc 
+        (count-down (- n 1) (+ acc 1))))
+";
+
+/// Name and source of each case
+const CASES: [(&str, &str); 3] = [
+    ("fib",        "(fib 20)"),
+    ("for-loop",   "(sum-to 50000)"),
+    ("tail-calls", "(count-down 50000 0)")
+];
+
+/// Runs of each case, the fastest is reported
+const RUNS: usize = 5;
+
+fn time(env: &mut Env, src: &str) -> Err<Duration> {
+    let mut best = Duration::MAX;
+
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        env.eval_str(src)?;
+        best = best.min(start.elapsed());
+    }
+
+    Ok(best)
+}
+
+fn run() -> Err {
+    let mut env = Env::new()?;
+    env.eval_str(SETUP)?;
+
+    println!("{:<12} {:>12} {:>12} {:>8}", "case", "tree-walk", "vm", "speedup");
+
+    for (name, src) in CASES {
+        env.set_interpreted(true);
+        let walk = time(&mut env, src)?;
+
+        env.set_interpreted(false);
+        let vm = time(&mut env, src)?;
+
+        let speedup = walk.as_secs_f64() / vm.as_secs_f64();
+        println!("{:<12} {:>12?} {:>12?} {:>7.2}x", name, walk, vm, speedup);
+    }
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,383 @@
+use std::path::PathBuf;
+
+use super::{
+    env::Env,
+    obj::Obj,
+    err::Err,
+    node::NodeIter,
+    sync::ThreadSafe,
+    limit::Limits,
+    fun::{Callable, FnBridge, HostFn}
+};
+
+/// Source of the native prelude definitions
+const PRELUDE: &str = include_str!("../scripts/prelude.lsp");
+/// Source name of the native prelude, as reported in errors
+const PRELUDE_PATH: &str = "src/scripts/prelude.lsp";
+
+/// Library of the host, installing its bridges into an `Env`
+type Library = dyn FnOnce(&mut Env);
+
+/// A group of bridges installed by `EnvBuilder`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Lib {
+    /// `set`, `defun`, `if`, `let` .. and the constants `True`, `False` and `Nil`
+    Std,
+    /// arithmetic, comparison and the constants `E` and `Pi`
+    Math,
+    /// `print`, `format`, and loading scripts with `load` and `require`
+    Io,
+    /// `list`, `nth`, `append` ..
+    List,
+    /// `hash-map`, `get`, `put` ..
+    Map,
+    /// `try`, `throw` and reading errors
+    Err,
+    /// `module`, `export` and `import`
+    Module
+}
+
+impl Lib {
+    /// Every group, as installed by `Env::new`
+    pub const ALL: [Lib; 7] = [Lib::Std, Lib::Math, Lib::Io, Lib::List, Lib::Map, Lib::Err, Lib::Module];
+
+    fn install(self, env: &mut Env) {
+        match self {
+            Lib::Std    => env.std_lib(),
+            Lib::Math   => env.math_lib(),
+            Lib::Io     => env.io_lib(),
+            Lib::List   => env.list_lib(),
+            Lib::Map    => env.map_lib(),
+            Lib::Err    => env.err_lib(),
+            Lib::Module => env.module_lib()
+        }
+    }
+
+    /// Returns the names of the callables in the group
+    fn callables(self) -> Vec<String> {
+        let mut env = Env::default();
+        self.install(&mut env);
+
+        let symbols = env.symbols.read();
+        symbols
+            .iter()
+            .filter(|(_, cell)| matches!(*cell.as_ref(), Obj::Bridge(_) | Obj::Native(_) | Obj::Macro(_)))
+            .map(|(sym, _)| sym.clone())
+            .collect()
+    }
+}
+
+/// Returns the names of the global symbols holding a macro
+fn macro_names(env: &Env) -> Vec<String> {
+    env.symbols
+        .read()
+        .iter()
+        .filter(|(_, cell)| matches!(*cell.as_ref(), Obj::Macro(_)))
+        .map(|(sym, _)| sym.clone())
+        .collect()
+}
+
+/// Configures and builds an `Env`
+///
+/// ## Example
+/// ```
+/// # use jester::{Env, TypeId};
+/// # fn main() -> jester::Err {
+/// let env = Env::builder()
+///     .path("scripts")
+///     .bridge("double", |env, args| {
+///         let x = args.get(0)?.eval(env)?.is_num()?;
+///         Ok((x * 2.0).as_obj())
+///     })
+///     .build()?;
+///
+/// let res = env.eval_str("(double 21)")?;
+/// # Ok(())
+/// # }
+/// ```
+/// 
+/// A sandbox can leave out groups of bridges and deny symbols,
+/// which then fail with a `capability` error when called
+/// ```
+/// # use jester::{Env, Lib};
+/// # fn main() -> jester::Err {
+/// let env = Env::builder()
+///     .libs([Lib::Std, Lib::Math, Lib::List])
+///     .deny("eval")
+///     .deny("gen-sym")
+///     .library(|env: &mut Env| {
+///         env.register("clamp", |x: f64| x.clamp(0.0, 1.0));
+///     })
+///     .build()?;
+/// 
+/// let err = env.eval_str("(print 1)").unwrap_err();
+/// assert_eq!(err.kind(), "capability");
+/// # Ok(())
+/// # }
+/// ```
+pub struct EnvBuilder {
+    prelude: bool,
+    interpreted: bool,
+    paths: Vec<PathBuf>,
+    bridges: Vec<FnBridge>,
+    limits: Limits,
+    libs: Vec<Lib>,
+    libraries: Vec<Box<Library>>,
+    denied: Vec<String>
+}
+
+impl Default for EnvBuilder {
+    fn default() -> Self {
+        Self {
+            prelude: true,
+            interpreted: false,
+            paths: Vec::new(),
+            bridges: Vec::new(),
+            limits: Limits::default(),
+            libs: Lib::ALL.to_vec(),
+            libraries: Vec::new(),
+            denied: Vec::new()
+        }
+    }
+}
+
+impl EnvBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether the prelude (`for`, `range`, `filter` ..)
+    /// is loaded, which it is by default
+    pub fn prelude(mut self, prelude: bool) -> Self {
+        self.prelude = prelude;
+        self
+    }
+
+    /// Sets whether function bodies are tree-walked
+    /// instead of compiled to bytecode
+    pub fn interpreted(mut self, interpreted: bool) -> Self {
+        self.interpreted = interpreted;
+        self
+    }
+
+    /// Sets the limits of each evaluation, which
+    /// don't apply to loading the prelude
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Sets the groups of bridges installed, which are all 
+    /// of them by default. The bridges of groups left out 
+    /// are denied, unless defined by the host or the prelude
+    /// 
+    /// ## Note
+    /// The prelude is defined with `Lib::Std`, and
+    /// its functions call into `Lib::Math` and `Lib::List`
+    pub fn libs<I: IntoIterator<Item = Lib>>(mut self, libs: I) -> Self {
+        self.libs = libs.into_iter().collect();
+        self
+    }
+
+    /// Adds a library of the host, installed after the chosen
+    /// groups and before the prelude is loaded
+    pub fn library<F>(mut self, lib: F) -> Self 
+    where
+        F: FnOnce(&mut Env) + 'static
+    {
+        self.libraries.push(Box::new(lib));
+        self
+    }
+
+    /// Denies symbol `sym` once the prelude is loaded, 
+    /// see `Env::deny`
+    /// 
+    /// ## Note
+    /// Prelude functions calling `sym` are denied too, 
+    /// i.e. denying `gen-sym` leaves out `range` and `filter`
+    pub fn deny(mut self, sym: &str) -> Self {
+        self.denied.push(sym.to_string());
+        self
+    }
+
+    /// Adds `dir` to the directories searched by `load` and `require`
+    pub fn path<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.paths.push(dir.into());
+        self
+    }
+
+    /// Adds a bridge named `sym`, defined before the prelude is loaded
+    pub fn bridge<F>(mut self, sym: &str, bridge: F) -> Self 
+    where
+        F: Fn(&Env, NodeIter) -> Err<Obj> + ThreadSafe + 'static
+    {
+        self.bridges.push(FnBridge::new(sym.to_string(), Box::new(bridge)));
+        self
+    }
+
+    /// Adds Rust function `fun` as a bridge named `sym`,
+    /// see `Env::register`
+    pub fn register<F, Args>(self, sym: &str, fun: F) -> Self 
+    where
+        F: HostFn<Args>
+    {
+        let name = sym.to_string();
+        self.bridge(sym, move |env, args| fun.call_host(&name, env, args))
+    }
+
+    /// Adds a bridge named `sym` that mutates the state it captures
+    pub fn bridge_mut<F>(mut self, sym: &str, bridge: F) -> Self 
+    where
+        F: FnMut(&Env, NodeIter) -> Err<Obj> + ThreadSafe + 'static
+    {
+        self.bridges.push(FnBridge::new_mut(sym.to_string(), Box::new(bridge)));
+        self
+    }
+
+    pub fn build(self) -> Err<Env> {
+        let mut env = Env::default();
+
+        for lib in self.libs.iter() {
+            lib.install(&mut env);
+        }
+
+        for lib in self.libraries {
+            lib(&mut env);
+        }
+
+        for bridge in self.bridges {
+            env.add_sym(&bridge.name().clone(), Obj::Bridge(bridge));
+        }
+
+        for dir in self.paths {
+            env.add_path(dir);
+        }
+
+        env.set_interpreted(self.interpreted);
+
+        let left_out = Lib::ALL
+            .into_iter()
+            .filter(|lib| !self.libs.contains(lib));
+
+        // denied before the prelude is read, which may 
+        // define them or call them from its macros
+        for sym in left_out.flat_map(Lib::callables) {
+            if env.get(&sym).is_none_or(|obj| matches!(obj, Obj::Nil(_))) {
+                env.deny(&sym)?;
+            }
+        }
+
+        if self.prelude {
+            let hosted = macro_names(&env);
+            env.eval_source(PRELUDE, PRELUDE_PATH)?;
+
+            // the prelude's macros only build forms from
+            // their args, so compiled bodies expand them once
+            for sym in macro_names(&env).iter().filter(|sym| !hosted.contains(sym)) {
+                if let Obj::Macro(f) = &mut *env.intern_sym(sym).as_mut()? {
+                    f.set_pure();
+                }
+            }
+        }
+
+        for sym in self.denied.iter() {
+            env.deny(sym)?;
+        }
+
+        env.set_limits(self.limits);
+
+        Ok(env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn libs_left_out_are_denied() {
+        let env = Env::builder()
+            .libs([Lib::Std, Lib::Math])
+            .build()
+            .unwrap();
+
+        assert_eq!(env.eval_str("(+ 1 2)").unwrap(), Obj::from(3));
+
+        let err = env.eval_str("(for i in 0 to 3 i)").unwrap_err();
+        assert_eq!(err.kind(), "capability");
+
+        let err = env.eval_str("[1 2]").unwrap_err();
+        assert_eq!(err.kind(), "capability");
+    }
+
+    /// Checks the callables of `left_out` that none of `libs`
+    /// define are denied, failing when called, unlike the rest
+    fn assert_denied(env: &Env, libs: &[Lib], left_out: &[Lib]) {
+        let is_denied = |sym: &str| matches!(env.get(sym), Some(Obj::Bridge(f)) if f.is_denied());
+
+        let granted: Vec<String> = libs
+            .iter()
+            .flat_map(|lib| lib.callables())
+            .collect();
+
+        for sym in granted.iter() {
+            assert!(!is_denied(sym), "{} is denied with {:?}", sym, libs);
+        }
+
+        for sym in left_out.iter().flat_map(|lib| lib.callables()) {
+            if granted.contains(&sym) {
+                continue
+            }
+
+            assert!(is_denied(&sym), "{} is granted with {:?}", sym, libs);
+
+            let err = env.eval_str(&format!("({})", sym)).unwrap_err();
+            assert_eq!(err.kind(), "capability", "{}", sym);
+        }
+    }
+
+    #[test]
+    fn any_libs_build() {
+        for mask in 0..1 << Lib::ALL.len() {
+            let libs: Vec<Lib> = Lib::ALL
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| mask & (1 << i) != 0)
+                .map(|(_, lib)| lib)
+                .collect();
+
+            let left_out: Vec<Lib> = Lib::ALL
+                .into_iter()
+                .filter(|lib| !libs.contains(lib))
+                .collect();
+
+            let env = Env::builder()
+                .libs(libs.clone())
+                .prelude(false)
+                .build()
+                .unwrap();
+
+            assert_denied(&env, &libs, &left_out);
+
+            // without `Lib::Std` the prelude can't be defined
+            match Env::builder().libs(libs.clone()).build() {
+                Ok(env) => {
+                    assert!(libs.contains(&Lib::Std));
+                    assert_denied(&env, &libs, &left_out);
+                }
+                Err(err) => {
+                    assert!(!libs.contains(&Lib::Std));
+                    assert_eq!(err.kind(), "capability");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn literals_without_their_bridge_fail() {
+        let env = Env::default();
+
+        let err = env.eval_str("{1 2}").unwrap_err();
+        assert_eq!(err.kind(), "capability");
+    }
+}
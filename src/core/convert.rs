@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+
+use super::{
+    obj::Obj::{self, *},
+    node::Node,
+    map::{Map, Key},
+    err::{Err, ErrObj, ErrType::{self, ErrCast}},
+    type_id::{TypeId, FromObj}
+};
+
+/// Converts `items` into `Obj::Vct`
+fn vector<I, T>(items: I) -> Obj
+where
+    I: IntoIterator<Item = T>,
+    T: Into<Obj>
+{
+    Vct(items
+        .into_iter()
+        .map(Into::into)
+        .collect::<Node>())
+}
+
+impl<T: TypeId> From<T> for Obj {
+    fn from(val: T) -> Self {
+        val.as_obj()
+    }
+}
+
+impl From<&str> for Obj {
+    fn from(val: &str) -> Self {
+        val.to_string().as_obj()
+    }
+}
+
+/// Implements `From` for unsigned integers, into the
+/// smallest integer `Obj` that holds every value
+macro_rules! from_uint {
+    ($($uint:ty => $int:ident),*) => {$(
+        impl From<$uint> for Obj {
+            fn from(val: $uint) -> Self {
+                $int(val.into())
+            }
+        }
+    )*};
+}
+
+from_uint!(u8 => I32, u16 => I32, u32 => I64, u64 => I128);
+
+impl From<usize> for Obj {
+    fn from(val: usize) -> Self {
+        // usize is at most 64 bits wide
+        I128(val as i128)
+    }
+}
+
+impl<T: Into<Obj>> From<Vec<T>> for Obj {
+    fn from(val: Vec<T>) -> Self {
+        vector(val)
+    }
+}
+
+/// ## Note
+/// Entries are inserted sorted by key,
+/// as `HashMap` has no order of its own
+impl<T: Into<Obj>> From<HashMap<String, T>> for Obj {
+    fn from(val: HashMap<String, T>) -> Self {
+        let mut entries = val
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        entries.sort_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs));
+
+        let mut map = Map::default();
+
+        for (key, val) in entries {
+            // strings are always hashable
+            map.insert(Key::new(Str(key)).unwrap(), val.into());
+        }
+
+        Obj::Map(map)
+    }
+}
+
+impl<T: Into<Obj>> From<Option<T>> for Obj {
+    fn from(val: Option<T>) -> Self {
+        match val {
+            Some(val) => val.into(),
+            None => Nil(())
+        }
+    }
+}
+
+impl<T: FromObj> FromObj for Vec<T> {
+    fn from_obj(obj: Obj) -> Err<Self> {
+        match obj {
+            Lst(x) | Vct(x) => x
+                .iter()
+                .map(|item| T::from_obj(item.clone_inner()))
+                .collect(),
+            _ => Err(obj.mis_type(Node::type_str()))
+        }
+    }
+}
+
+impl<T: FromObj> FromObj for HashMap<String, T> {
+    fn from_obj(obj: Obj) -> Err<Self> {
+        match obj {
+            Obj::Map(x) => x
+                .iter()
+                .map(|(key, val)| Ok((
+                    String::from_obj(key.as_obj().clone())?,
+                    T::from_obj(val.clone())?
+                )))
+                .collect(),
+            _ => Err(obj.mis_type(Map::type_str()))
+        }
+    }
+}
+
+impl<T: FromObj> FromObj for Option<T> {
+    fn from_obj(obj: Obj) -> Err<Self> {
+        match obj {
+            Nil(_) => Ok(None),
+            obj => T::from_obj(obj).map(Some)
+        }
+    }
+}
+
+/// Implements `TryFrom<Obj>` for types that are `FromObj`
+macro_rules! try_from {
+    ($($ty:ty),*) => {$(
+        impl TryFrom<Obj> for $ty {
+            type Error = ErrType;
+
+            fn try_from(obj: Obj) -> Err<Self> {
+                <$ty>::from_obj(obj)
+            }
+        }
+    )*};
+}
+
+try_from!(f64, i32, i64, i128, u8, u16, u32, u64, usize, bool, String, Node, Map, ErrObj, ());
+
+// `Option<Obj>` is already `From<Obj>`, so `Option<T>` can't be generic
+try_from!(
+    Option<f64>, Option<i32>, Option<i64>, Option<i128>, 
+    Option<u8>, Option<u16>, Option<u32>, Option<u64>, Option<usize>, 
+    Option<bool>, Option<String>, Option<Node>, Option<Map>, Option<ErrObj>
+);
+
+impl<T: FromObj> TryFrom<Obj> for Vec<T> {
+    type Error = ErrType;
+
+    fn try_from(obj: Obj) -> Err<Self> {
+        Self::from_obj(obj)
+    }
+}
+
+impl<T: FromObj> TryFrom<Obj> for HashMap<String, T> {
+    type Error = ErrType;
+
+    fn try_from(obj: Obj) -> Err<Self> {
+        Self::from_obj(obj)
+    }
+}
+
+impl<'a> TryFrom<&'a Obj> for &'a str {
+    type Error = ErrType;
+
+    fn try_from(obj: &'a Obj) -> Err<Self> {
+        obj.is_str()
+    }
+}
+
+/// Implements the conversions between tuples
+/// of `$ty` and vectors of the same length
+macro_rules! tuple {
+    ($len:literal; $($ty:ident),*) => {
+        impl<$($ty: Into<Obj>),*> From<($($ty,)*)> for Obj {
+            #[allow(non_snake_case)]
+            fn from(($($ty,)*): ($($ty,)*)) -> Self {
+                vector([$($ty.into()),*])
+            }
+        }
+
+        impl<$($ty: FromObj),*> FromObj for ($($ty,)*) {
+            fn from_obj(obj: Obj) -> Err<Self> {
+                let node = match obj {
+                    Lst(x) | Vct(x) if x.len() == $len => x,
+                    Lst(ref x) | Vct(ref x) => return Err(ErrCast {
+                        expected: format!("tuple of {}", $len),
+                        found: format!("{} of {}", obj.type_string(), x.len())
+                    }),
+                    _ => return Err(obj.mis_type(Node::type_str()))
+                };
+
+                let mut items = node.iter();
+
+                Ok(($($ty::from_obj(items.next().unwrap().clone_inner())?,)*))
+            }
+        }
+
+        impl<$($ty: FromObj),*> TryFrom<Obj> for ($($ty,)*) {
+            type Error = ErrType;
+
+            fn try_from(obj: Obj) -> Err<Self> {
+                Self::from_obj(obj)
+            }
+        }
+    };
+}
+
+tuple!(1; A);
+tuple!(2; A, B);
+tuple!(3; A, B, C);
+tuple!(4; A, B, C, D);
+tuple!(5; A, B, C, D, E);
+tuple!(6; A, B, C, D, E, F);
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::core::{env::Env, obj::Obj};
+
+    #[test]
+    fn collections_round_trip() {
+        let obj = Obj::from(vec![1_i64, 2, 3]);
+        assert_eq!(Vec::<i64>::try_from(obj.clone()).unwrap(), [1, 2, 3]);
+        assert_eq!(obj.to_string(), "[1 2 3]");
+
+        let map = HashMap::from([
+            ("b".to_string(), vec![2]),
+            ("a".to_string(), vec![1, 1])
+        ]);
+
+        let obj = Obj::from(map.clone());
+        assert_eq!(HashMap::<String, Vec<i32>>::try_from(obj.clone()).unwrap(), map);
+        assert_eq!(obj.to_string(), "{a [1 1] b [2]}");
+        assert_eq!(format!("{:?}", obj), "{\"a\" [1 1] \"b\" [2]}");
+
+        let obj = Obj::from((1, "two", Some(3.5), None::<bool>));
+        assert_eq!(<(i32, String, Option<f64>, Option<bool>)>::try_from(obj).unwrap(), (1, "two".to_string(), Some(3.5), None));
+    }
+
+    #[test]
+    fn script_values_convert() {
+        let env = Env::new().unwrap();
+
+        let obj = env.eval_str("(list 1 2 (* 3 3))").unwrap();
+        assert_eq!(Vec::<u8>::try_from(obj).unwrap(), [1, 2, 9]);
+
+        let obj = env.eval_str("{\"x\" 1 \"y\" 2}").unwrap();
+        let map = HashMap::<String, usize>::try_from(obj).unwrap();
+        assert_eq!((map["x"], map["y"]), (1, 2));
+
+        assert_eq!(Obj::from(u64::MAX), env.eval_str("18446744073709551615").unwrap());
+        assert_eq!(Obj::from(2), Obj::from(2.0));
+    }
+
+    #[test]
+    fn mismatched_values_fail() {
+        let err = Vec::<i32>::try_from(Obj::from(vec!["a"])).unwrap_err();
+        assert_eq!(err.kind(), "type");
+
+        let err = <(i32, i32)>::try_from(Obj::from(vec![1, 2, 3])).unwrap_err();
+        assert_eq!(err.kind(), "cast");
+
+        let err = u8::try_from(Obj::from(300)).unwrap_err();
+        assert_eq!(err.kind(), "cast");
+    }
+}
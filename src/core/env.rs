@@ -1,49 +1,201 @@
 use std::{
-    collections::HashMap, 
-    io::Write, 
-    time::{Duration, Instant}
+    collections::{HashMap, HashSet}, 
+    path::PathBuf
 };
 
 use super::{
     obj::Obj,
-    err::Err,
-    fun::Bridge, 
+    err::{Err, ErrType, Backtrace, CallSite},
+    fun::{TailBridge, FnBridge, HostFn}, 
+    node::NodeIter,
     rc_cell::RcCell,
+    sync::{Shared, Weak, Lock, ThreadSafe},
+    scope::Scope,
+    module::Module,
+    builder::EnvBuilder,
+    limit::{Meter, Work},
     type_id::Primitive, id::Id
 };
 
-// TODO: work on namespaces - module tree qualifiers
 
-/// Path to native prelude definitions 
-const PRELUDE_PATH: &str = "src/scripts/prelude.lsp";
-/// String introduction for `REPL` mode
-const REPL_HEADER: &str = "Welcome to Jester Script, the Rust-Lisp Scripting Langauge!\nDeveloped by Mattan Mohel, 2021-2023";
+/// A function call on the `Env` call stack
+#[derive(Clone)]
+struct Call {
+    name: String,
+    form: Obj
+}
 
+/// State of the evaluation a thread runs on an `Env`
+#[derive(Default)]
+pub(crate) struct Context {
+    scope: Lock<Option<RcCell<Scope>>>,
+    calls: Lock<Vec<Call>>,
+    /// scripts being loaded, innermost last
+    pub(crate) loading: Lock<Vec<PathBuf>>,
+    pub(crate) work: Work
+}
 
-/// `Jester-rs` Environment struct
-#[derive(Clone)]
-pub struct Env {
-    symbols: HashMap<String, RcCell<Obj>>,
+impl Context {
+    /// Returns the current scope and call depth
+    pub(crate) fn save(&self) -> (Option<RcCell<Scope>>, usize) {
+        (self.scope.read().clone(), self.calls.read().len())
+    }
+
+    /// Restores the scope and call depth from `save`
+    pub(crate) fn restore(&self, scope: Option<RcCell<Scope>>, depth: usize) {
+        *self.scope.write() = scope;
+        self.calls.write().truncate(depth);
+    }
+
+    fn is_idle(&self) -> bool {
+        self.scope.read().is_none() 
+            && self.calls.read().is_empty() 
+            && self.loading.read().is_empty()
+    }
+}
+
+#[cfg(feature = "sync")]
+thread_local! {
+    /// `Context` of each `Env` this thread evaluates on, 
+    /// by the id of its `Contexts`, which are few
+    static CONTEXTS: std::cell::RefCell<Vec<(usize, Shared<Context>)>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// The `Context` of each thread evaluating on an `Env`
+///
+/// ## Note
+/// Without the `sync` feature an `Env` stays on its thread,
+/// so it has a single `Context`. With it, each thread keeps
+/// its own in `CONTEXTS`. A clone of an `Env` starts with 
+/// no evaluation running
+#[cfg(not(feature = "sync"))]
+#[derive(Default)]
+struct Contexts {
+    local: Shared<Context>
+}
+
+/// The key of the `Context` each thread keeps in `CONTEXTS`
+#[cfg(feature = "sync")]
+struct Contexts {
+    id: usize
 }
 
-impl Default for Env {
+#[cfg(feature = "sync")]
+impl Default for Contexts {
     fn default() -> Self {
-        Self { 
-            symbols: HashMap::new() 
+        Self {
+            id: Id::next_id()
         }
     }
 }
 
+impl Clone for Contexts {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(not(feature = "sync"))]
+impl Contexts {
+    fn current(&self) -> Shared<Context> {
+        Shared::clone(&self.local)
+    }
+
+    fn release(&self) {}
+}
+
+#[cfg(feature = "sync")]
+impl Contexts {
+    fn current(&self) -> Shared<Context> {
+        let id = self.id;
+
+        CONTEXTS.with_borrow_mut(|contexts| {
+            match contexts.iter().find(|(rhs, _)| *rhs == id) {
+                Some((_, context)) => Shared::clone(context),
+                None => {
+                    let context = Shared::new(Context::default());
+                    contexts.push((id, Shared::clone(&context)));
+                    context
+                }
+            }
+        })
+    }
+
+    /// Drops the `Context` of this thread once no
+    /// evaluation is running and no scope is set
+    fn release(&self) {
+        let id = self.id;
+
+        CONTEXTS.with_borrow_mut(|contexts| contexts.retain(|(rhs, context)| {
+            *rhs != id || !(context.is_idle() && context.work.is_idle())
+        }));
+    }
+}
+
+#[cfg(feature = "sync")]
+impl Drop for Contexts {
+    fn drop(&mut self) {
+        // other threads dropped theirs as their evaluations returned
+        let id = self.id;
+        let _ = CONTEXTS.try_with(|contexts| contexts.borrow_mut().retain(|(rhs, _)| *rhs != id));
+    }
+}
+
+/// `Jester-rs` Environment struct
+#[derive(Clone, Default)]
+pub struct Env {
+    pub(crate) symbols: Lock<HashMap<String, RcCell<Obj>>>,
+    /// symbols made by `gen_sym`, freed once unreferenced
+    pub(crate) gensyms: Lock<HashMap<String, Weak<Lock<Obj>>>>,
+    /// modules by name or alias
+    pub(crate) modules: Lock<HashMap<String, Shared<Module>>>,
+    /// unqualified names brought in by `import`
+    pub(crate) imports: Lock<HashMap<String, RcCell<Obj>>>,
+    /// directories added by the host to search for scripts
+    pub(crate) paths: Lock<Vec<PathBuf>>,
+    /// scripts loaded by `require`
+    pub(crate) loaded: Lock<HashSet<PathBuf>>,
+    /// the scope, call stack and work of each thread's evaluation
+    contexts: Contexts,
+    interpreted: Lock<bool>,
+    pub(crate) meter: Meter
+}
+
 impl Env {
+    /// Creates an `Env` with every library and the prelude
     pub fn new() -> Err<Self> {
-        let mut env = Self::default();
-        env.math_lib();
-        env.std_lib();
-        env.io_lib();
-        env.list_lib();
-        env.add_from_file(PRELUDE_PATH)?;
-            
-        Ok(env)
+        Self::builder().build()
+    }
+
+    pub fn builder() -> EnvBuilder {
+        EnvBuilder::new()
+    }
+
+    /// Evaluates `src`, returning the value of its last form
+    pub fn eval_str(&self, src: &str) -> Err<Obj> {
+        self.add_from_string(src)
+    }
+
+    /// Returns the value of the item imported as `sym`, or
+    /// else of global symbol `sym`, as the reader binds it
+    pub fn get(&self, sym: &str) -> Option<Obj> {
+        let imported = self.imports
+            .read()
+            .get(sym)
+            .cloned();
+
+        imported
+            .or_else(|| self.get_sym(sym))
+            .map(|cell| cell.clone_inner())
+    }
+
+    /// Sets global symbol `sym` to `val`, adding it if it doesn't exist
+    /// 
+    /// ## Note
+    /// Fails with a `Borrowed` error if the value of `sym` is being read
+    pub fn set(&self, sym: &str, val: Obj) -> Err {
+        self.intern_sym(sym).replace(val)?;
+        Ok(())
     }
 
     pub fn unique_sym() -> String {
@@ -51,98 +203,377 @@ impl Env {
     }   
 
     pub fn add_sym(&mut self, sym: &str, val: Obj) -> RcCell<Obj> {
-        let pop = self.symbols.insert(sym.to_string(), RcCell::from(val));
+        let cell = RcCell::from(val);
+        let pop = self.symbols
+            .get_mut()
+            .insert(sym.to_string(), cell.clone());
+
         assert!(pop.is_none(), "\"{}\" already exists!", sym);
-        self.symbols[sym].clone()
+        cell
+    }
+
+    /// Returns the cell of symbol `sym`, adding 
+    /// it with a `nil` value if it doesn't exist
+    pub(crate) fn intern_sym(&self, sym: &str) -> RcCell<Obj> {
+        self.symbols
+            .write()
+            .entry(sym.to_string())
+            .or_insert_with(|| RcCell::from(Obj::Nil(())))
+            .clone()
     }
 
     pub fn get_sym(&self, sym: &str) -> Option<RcCell<Obj>> {
         self.symbols
+            .read()
             .get(sym)
             .cloned()
     }
 
     pub fn has_sym(&self, sym: &str) -> bool {
         self.symbols
-            .keys()
-            .any(|rhs| sym == rhs)
+            .read()
+            .contains_key(sym)
     } 
 
+    /// Returns the names of the global and imported 
+    /// symbols holding a value, sorted
+    pub fn sym_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.symbols
+            .read()
+            .iter()
+            .chain(self.imports.read().iter())
+            .filter(|(_, cell)| !matches!(*cell.as_ref(), Obj::Nil(_)))
+            .map(|(sym, _)| sym.clone())
+            .collect();
+
+        names.sort();
+        names.dedup();
+        names
+    }
+
     pub fn get_sym_id(&self, obj: &RcCell<Obj>) -> Option<String> {
-        self.symbols
+        self
+            .get_sym_name(obj)
+            .map(|name| name.to_uppercase())
+    }
+
+    /// Returns the name of symbol `obj` as it was written
+    pub fn get_sym_name(&self, obj: &RcCell<Obj>) -> Option<String> {
+        let named = self.symbols
+            .read()
             .iter()
             .find_map(|rhs| {
                 if obj.raw_eq(rhs.1) {
-                    Some(rhs.0.to_uppercase())
+                    Some(rhs.0.clone())
                 }
                 else {
                     None
                 }
-            })
-            
+            });
+
+        named.or_else(|| self.gensyms
+            .read()
+            .iter()
+            .find(|(_, rhs)| std::ptr::eq(rhs.as_ptr(), Shared::as_ptr(obj.as_raw())))
+            .map(|(name, _)| name.clone()))
+    }
+
+    /// Returns the cell holding the value of symbol `sym`,
+    /// its binding in the current scope or its global cell
+    pub fn lookup(&self, sym: &RcCell<Obj>) -> RcCell<Obj> {
+        self.scope()
+            .and_then(|scope| scope.as_ref().lookup(sym))
+            .unwrap_or_else(|| sym.clone())
+    }
+
+    /// Returns the cell holding the value of `cell`: the 
+    /// binding of the symbol it holds, or `cell` itself
+    pub fn resolve(&self, cell: &RcCell<Obj>) -> RcCell<Obj> {
+        match &*cell.as_ref() {
+            Obj::Sym(sym) => self.lookup(sym),
+            _ => cell.clone()
+        }
+    }
+
+    /// Returns the cell holding arg `cell`: the binding of a
+    /// symbol, so bridges can change it in place, or a new
+    /// cell holding what any other form evaluates to
+    pub fn eval_cell(&self, cell: &RcCell<Obj>) -> Err<RcCell<Obj>> {
+        if let Obj::Sym(sym) = &*cell.as_ref() {
+            return Ok(self.lookup(sym))
+        }
+
+        let obj = self.eval(&cell.as_ref())?;
+        Ok(RcCell::from(obj))
+    }
+
+    /// Returns whether function bodies are tree-walked 
+    /// instead of compiled to bytecode
+    pub fn interpreted(&self) -> bool {
+        *self.interpreted.read()
+    }
+
+    /// Sets whether function bodies are tree-walked
+    /// instead of compiled to bytecode
+    pub fn set_interpreted(&self, interpreted: bool) {
+        *self.interpreted.write() = interpreted;
+    }
+
+    /// Returns the state of the evaluation on this thread
+    pub(crate) fn context(&self) -> Shared<Context> {
+        self.contexts.current()
+    }
+
+    /// Drops `context`, the state of the evaluation on this 
+    /// thread, if none is running, once an evaluation returns
+    pub(crate) fn release_context(&self, context: &Context) {
+        if context.work.is_idle() {
+            self.contexts.release();
+        }
+    }
+
+    /// Returns the current lexical scope of this thread
+    pub fn scope(&self) -> Option<RcCell<Scope>> {
+        self.context().scope.read().clone()
+    }
+
+    /// Replaces the current lexical scope of this
+    /// thread, returning the previous
+    pub fn set_scope(&self, scope: Option<RcCell<Scope>>) -> Option<RcCell<Scope>> {
+        std::mem::replace(&mut *self.context().scope.write(), scope)
+    }
+
+    /// Runs `body` within `scope`, restoring the current scope after
+    pub fn in_scope<F>(&self, scope: RcCell<Scope>, body: F) -> Err<Obj> 
+    where
+        F: FnOnce() -> Err<Obj>
+    {
+        let prev = self.set_scope(Some(scope));
+        let res = body();
+        self.set_scope(prev);
+
+        res
     }
 
     pub fn add_primitive<T: Primitive>(&mut self, sym: &str, prim: T) -> RcCell<Obj> {
         self.add_sym(sym, prim.as_obj())
     }
 
-    pub fn add_bridge(&mut self, sym: &str, bridge: Bridge) -> RcCell<Obj> {
-        let obj = Obj::new_bridge(sym.to_string(), bridge);
+    /// Adds a bridge, which may be a closure capturing host state
+    pub fn add_bridge<F>(&mut self, sym: &str, bridge: F) -> RcCell<Obj> 
+    where
+        F: Fn(&Env, NodeIter) -> Err<Obj> + ThreadSafe + 'static
+    {
+        let obj = Obj::new_bridge(sym.to_string(), Box::new(bridge));
+        self.add_sym(sym, obj)
+    }
+
+    /// Adds Rust function `fun` as a bridge, which evaluates
+    /// its args and converts them to the types of its params
+    /// 
+    /// ## Example
+    /// ```
+    /// # let mut env = jester::Env::new().unwrap();
+    /// env.register("hypot", |a: f64, b: f64| a.hypot(b));
+    /// env.register("repeat", |s: String, n: i64| s.repeat(n as usize));
+    /// ```
+    /// 
+    /// ## Note
+    /// A call with the wrong number of args is an `arity` error,
+    /// and an arg of the wrong type a `type` error naming it
+    pub fn register<F, Args>(&mut self, sym: &str, fun: F) -> RcCell<Obj> 
+    where
+        F: HostFn<Args>
+    {
+        let name = sym.to_string();
+        self.add_bridge(sym, move |env, args| fun.call_host(&name, env, args))
+    }
+
+    /// Adds a bridge that mutates the state it captures
+    /// 
+    /// ## Example
+    /// ```
+    /// # use jester::TypeId;
+    /// # let mut env = jester::Env::new().unwrap();
+    /// let mut count: i64 = 0;
+    /// 
+    /// env.add_bridge_mut("tick", move |_, _| {
+    ///     count += 1;
+    ///     Ok(count.as_obj())
+    /// });
+    /// ```
+    pub fn add_bridge_mut<F>(&mut self, sym: &str, bridge: F) -> RcCell<Obj> 
+    where
+        F: FnMut(&Env, NodeIter) -> Err<Obj> + ThreadSafe + 'static
+    {
+        let obj = Obj::Bridge(FnBridge::new_mut(sym.to_string(), Box::new(bridge)));
+        self.add_sym(sym, obj)
+    }
+
+    /// Adds a bridge that can leave a form in tail position,
+    /// evaluated by `Env::eval` without growing the Rust stack
+    pub fn add_tail_bridge(&mut self, sym: &str, bridge: TailBridge) -> RcCell<Obj> {
+        let obj = Obj::Bridge(FnBridge::new_tail(sym.to_string(), bridge));
         self.add_sym(sym, obj)
     }
 
-    pub fn gen_sym(&mut self, obj: Obj) -> RcCell<Obj> {   
+    /// Sets global symbol `sym` to a bridge failing with a 
+    /// `Denied` error, so scripts can't reach what it held
+    /// 
+    /// ## Note
+    /// The symbol keeps its cell, so functions and
+    /// modules already referring to it are denied too
+    pub fn deny(&self, sym: &str) -> Err {
+        self.set(sym, Obj::Bridge(FnBridge::denied(sym.to_string())))
+    }
+
+    /// Returns a new symbol holding `obj`, named `G#n`
+    /// 
+    /// ## Note
+    /// Unlike named symbols, it isn't kept by the symbol
+    /// table, so it is freed once nothing refers to it
+    pub fn gen_sym(&self, obj: Obj) -> RcCell<Obj> {   
         let sym = Env::unique_sym();
-        self.add_sym(sym.as_str(), obj)
+        let cell = RcCell::from(obj);
+
+        self.gensyms
+            .write()
+            .insert(sym, cell.downgrade());
+
+        cell
     }
 
-    pub unsafe fn gen_sym_runtime(&self, obj: Obj) -> RcCell<Obj> {   
-        // coerce self mutability   
-        let ptr = (self as *const Self) as *mut Self;
-            
-        match ptr.as_mut() {
-            Some(env) => {
-                let sym = Env::unique_sym();
-                env.add_sym(sym.as_str(), obj)
-            }
-            None => panic!("environment not initialized!")
-        } 
+    /// Returns whether no form is being evaluated on this thread
+    pub(crate) fn at_top_level(&self) -> bool {
+        self.context().is_idle()
     }
     
-    pub fn repl(&mut self) -> Err {
-        println!("{}", REPL_HEADER);
+    /// Runs `call` with `name` pushed on the call stack, 
+    /// attaching a `Backtrace` to any error it returns
+    pub fn with_call<F>(&self, name: &str, form: &Obj, call: F) -> Err<Obj> 
+    where
+        F: FnOnce() -> Err<Obj>
+    {
+        self.context().calls.write().push(Call { 
+            name: name.to_string(), 
+            form: form.clone() 
+        });
 
-        let mut time = Duration::new(0, 0);
+        let res = call().map_err(|err| self.trace(err));
+        self.context().calls.write().pop();
+
+        res
+    }
 
-        loop {
-            print!(">> ");
-            std::io::stdout().flush()?;
+    /// Pushes a call onto the call stack, replacing
+    /// the innermost call if `tail` is set
+    pub(crate) fn push_call(&self, name: &str, form: &Obj, tail: bool) {
+        let call = Call { 
+            name: name.to_string(), 
+            form: form.clone() 
+        };
 
-            let mut input = String::new();
-            std::io::stdin().read_line(&mut input)?;
+        let context = self.context();
+        let mut calls = context.calls.write();
+
+        if tail {
+            calls.pop();
+        }
+
+        calls.push(call);
+    }
 
-            match input.trim() {
-                "--help" => {
-                    unimplemented!()
+    /// Returns the number of calls on the call stack
+    pub(crate) fn call_depth(&self) -> usize {
+        self.context().calls.read().len()
+    }
+
+    /// Attaches the current call stack to `err`, only 
+    /// building it if `err` has none, see `ErrType::traced`
+    pub(crate) fn trace(&self, err: ErrType) -> ErrType {
+        match err.backtrace() {
+            Some(_) => err,
+            None => err.traced(self.backtrace())
+        }
+    }
+
+    /// Returns the call stack of this thread, innermost call first
+    pub fn backtrace(&self) -> Backtrace {
+        let calls = self.context()
+            .calls
+            .read()
+            .iter()
+            .rev()
+            .map(|call| CallSite {
+                name: call.name.clone(),
+                form: call.form.as_string(self),
+                span: match &call.form {
+                    Obj::Lst(node) => node.span().cloned(),
+                    _ => None
                 }
-                "--quit" => {
-                    println!("quitting...");
-                    break;
-                },
-                "--time" => {
-                    println!("completed in: {:?}...", time);
-                    continue;
-                },
-                _ => ()
+            })
+            .collect();
+
+        Backtrace { calls }
+    }
+}
+
+#[cfg(all(test, feature = "sync"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threads_evaluate_on_a_shared_env() {
+        let env = Env::new().unwrap();
+
+        env.eval_str("
+            (defun sum-to (n)
+                (let (acc 0)
+                    (for i in 0 to n
+                        (set acc (+ acc i)))
+                    acc))
+
+            (defun fact (n acc) 
+                (if (= n 0) acc (fact (- n 1) (* n acc))))
+        ").unwrap();
+
+        std::thread::scope(|threads| {
+            for n in 1..=8 {
+                let env = &env;
+
+                threads.spawn(move || {
+                    for _ in 0..20 {
+                        let src = format!("(let (n {}) (list (sum-to (* n 10)) (fact n 1)))", n);
+                        let res = env.eval_str(&src).unwrap();
+                        let sum = (n * 10) * (n * 10 - 1) / 2;
+                        let fact: i32 = (1..=n).product();
+
+                        assert_eq!(res.display(env), format!("({} {})", sum, fact));
+                    }
+
+                    let err = env.eval_str("(fact 3 \"a\")").unwrap_err();
+                    let trace = err.backtrace().unwrap();
+                    assert!(trace.calls.iter().all(|call| call.name == "FACT"));
+                });
             }
+        });
 
-            let start = Instant::now();
-            let res = self.add_from_string(&input.trim().to_string())?;
-            time = start.elapsed();
+        assert!(env.at_top_level());
+    }
 
-            println!("{}", res.display(self));
-        }
+    #[test]
+    fn clones_share_globals_across_threads() {
+        let env = Env::new().unwrap();
+        env.eval_str("(defun square (x) (* x x)) (set shared (list))").unwrap();
 
-        Ok(())
+        let worker = env.clone();
+
+        std::thread::spawn(move || {
+            worker.eval_str("(append (square 7) shared) (set local 1)").unwrap();
+        }).join().unwrap();
+
+        assert_eq!(env.eval_str("shared").unwrap().display(&env), "(49)");
+        // symbols made after cloning are the clone's own
+        assert_eq!(env.eval_str("local").unwrap().display(&env), "nil");
     }
-}
\ No newline at end of file
+}
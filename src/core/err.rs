@@ -1,40 +1,591 @@
 use std::{
     error::Error,
-    fmt::Display,
-    io
+    fmt::{Debug, Display},
+    io,
+    time::Duration
+};
+
+use super::{
+    lex::Span,
+    obj::Obj,
+    sync::{Shared, ThreadSafe},
+    gc::{Trace, Tracer}
 };
 
 pub type Err<T = ()> = Result<T, ErrType>;
 
+/// Error returned by the host application
+#[cfg(not(feature = "sync"))]
+pub type HostErr = dyn Error;
+
+/// Error returned by the host application
+#[cfg(feature = "sync")]
+pub type HostErr = dyn Error + Send + Sync;
+
+/// A function call active when an error was raised
+#[derive(Clone, Debug)]
+pub struct CallSite {
+    /// name of the called function
+    pub name: String,
+    /// the calling form, as source
+    pub form: String,
+    /// location of the calling form, if known
+    pub span: Option<Span>
+}
+
+/// Calls printed at each end of a long `Backtrace`
+const TRACE_ENDS: usize = 8;
+
+/// Script-level call stack, innermost call first
+#[derive(Clone, Debug, Default)]
+pub struct Backtrace {
+    pub calls: Vec<CallSite>
+}
+
+/// Prints the calls innermost first, folding runs of calls to 
+/// the same function into `f ×n`, and only the `TRACE_ENDS` 
+/// runs at each end of a longer stack, as in deep recursion
+/// ```text
+/// in F ×3998 <- in G <- toplevel
+/// ```
+impl Display for Backtrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut runs: Vec<(&String, usize)> = Vec::new();
+
+        for call in self.calls.iter() {
+            match runs.last_mut() {
+                Some((name, n)) if **name == call.name => *n += 1,
+                _ => runs.push((&call.name, 1))
+            }
+        }
+
+        let elided = runs.len().saturating_sub(2 * TRACE_ENDS);
+
+        for (i, (name, n)) in runs.iter().enumerate() {
+            if elided > 0 && i == TRACE_ENDS {
+                write!(f, "... {} more <- ", elided)?;
+            }
+
+            if elided > 0 && i >= TRACE_ENDS && i < TRACE_ENDS + elided {
+                continue
+            }
+
+            match n {
+                1 => write!(f, "in {} <- ", name)?,
+                n => write!(f, "in {} ×{} <- ", name, n)?
+            }
+        }
+
+        f.write_str("toplevel")
+    }
+}
+
+/// An error as a script value, made by `throw` 
+/// or when `try` catches an `ErrType`
+/// 
+/// ## Example
+/// ```text
+/// (try 
+///     (assert False)
+///     (catch err
+///         (error-kind err)))    ; returns "assert"
+/// 
+/// (try
+///     (throw '(not-found 404))
+///     (catch err
+///         (error-payload err))) ; returns (not-found 404)
+/// ```
+#[derive(Clone)]
+pub struct ErrObj {
+    info: Shared<ErrInfo>
+}
+
+struct ErrInfo {
+    kind: String,
+    message: String,
+    payload: Obj,
+    span: Option<Span>,
+    trace: Option<Backtrace>
+}
+
+impl Trace for ErrInfo {
+    fn trace(&self, tracer: &mut Tracer) {
+        self.payload.trace(tracer);
+    }
+}
+
+impl Trace for ErrObj {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.rc(&self.info);
+    }
+}
+
+impl ErrObj {
+    /// Kind of the errors raised by `throw`
+    pub const THROWN: &'static str = "throw";
+
+    pub fn new(kind: &str, message: String, payload: Obj) -> Self {
+        Self {
+            info: Shared::new(ErrInfo {
+                kind: kind.to_string(),
+                message,
+                payload,
+                span: None,
+                trace: None
+            })
+        }
+    }
+
+    /// Returns the kind of error, i.e. `"type"` or `"throw"`
+    pub fn kind(&self) -> &str {
+        &self.info.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.info.message
+    }
+
+    /// Returns the value thrown, or `nil` if 
+    /// the error wasn't raised by `throw`
+    pub fn payload(&self) -> &Obj {
+        &self.info.payload
+    }
+
+    pub fn span(&self) -> Option<&Span> {
+        self.info.span.as_ref()
+    }
+
+    pub fn trace(&self) -> Option<&Backtrace> {
+        self.info.trace.as_ref()
+    }
+
+    /// Fills in the location and call stack, if missing
+    fn located(self, span: Option<&Span>, trace: Option<&Backtrace>) -> Self {
+        let info = &self.info;
+
+        if (info.span.is_some() || span.is_none()) && (info.trace.is_some() || trace.is_none()) {
+            return self
+        }
+
+        Self {
+            info: Shared::new(ErrInfo {
+                kind: info.kind.clone(),
+                message: info.message.clone(),
+                payload: info.payload.clone(),
+                span: info.span.clone().or_else(|| span.cloned()),
+                trace: info.trace.clone().or_else(|| trace.cloned())
+            })
+        }
+    }
+}
+
+impl Debug for ErrObj {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ErrObj")
+            .field("kind", &self.info.kind)
+            .field("message", &self.info.message)
+            .field("span", &self.info.span)
+            .finish()
+    }
+}
+
 #[derive(Debug)]
 pub enum ErrType {
-    IoErr,
-    NonSym,
-    DupSym,
-    NonMod,
-    DupMod, 
-    Params,
-    MisType,
-    MisComp,
-    MisForm,
-    ErrCast,
+    /// Failed io operation
+    IoErr(io::Error),
+    /// Undefined symbol
+    NonSym(String),
+    /// Symbol defined twice
+    DupSym(String),
+    /// Undefined module
+    NonMod(String),
+    /// Module defined twice
+    DupMod(String), 
+    /// Script required while it is being loaded
+    CycMod(String),
+    /// Wrong number of arguments
+    Params {
+        expected: usize,
+        found: usize,
+        variadic: bool
+    },
+    /// Unexpected type
+    MisType {
+        expected: String,
+        found: String
+    },
+    /// Unexpected type for an argument of a registered function
+    MisArg {
+        sym: String,
+        index: usize,
+        expected: String,
+        found: String
+    },
+    /// Uncomparable types
+    MisComp {
+        lhs: String,
+        rhs: String
+    },
+    /// Malformed expression or literal
+    MisForm(String),
+    /// Failed type coercion
+    ErrCast {
+        expected: String,
+        found: String
+    },
     ErrList,
-    Overflow,
-    Unbalanced,  
-    OutOfBound,
-    RuntimeAssert
+    /// Numeric literal too large
+    Overflow(String),
+    /// Integer operation, as `lhs op rhs`, out of range of its type
+    NumOverflow(String),
+    /// Integer divided by zero
+    DivZero,
+    /// Bracket left unclosed, closed twice or closed by another
+    Unbalanced(String),
+    /// Index past the end of a list
+    OutOfBound {
+        index: usize,
+        len: usize
+    },
+    /// Failed assertion, with a user message
+    RuntimeAssert(String),
+    /// Mutable bridge called while it is already running
+    ReEntry(String),
+    /// Value written while it is being read
+    Borrowed,
+    /// Capability the host didn't grant
+    Denied(String),
+    /// Evaluation ran more steps than its limit
+    StepLimit(usize),
+    /// Evaluation nested deeper than its limit
+    DepthLimit(usize),
+    /// Evaluation made more list elements and string bytes than its limit
+    AllocLimit(usize),
+    /// Evaluation ran longer than its limit
+    TimeLimit(Duration),
+    /// Evaluation cancelled by the host
+    Cancelled,
+    /// Error payload returned by the host application
+    Host(Box<HostErr>),
+    /// Error raised by the form at `Span`
+    Located(Box<ErrType>, Span),
+    /// Error with the call stack it escaped from
+    Traced(Box<ErrType>, Backtrace),
+    /// Error raised by `throw`, or rethrown after `try` caught it
+    Thrown(ErrObj)
 }
 
-impl Error for ErrType {}
+impl ErrType {
+    /// Wraps a host application error
+    /// 
+    /// ## Example
+    /// ```
+    /// # use jester::{ErrType, TypeId};
+    /// # let mut env = jester::Env::new().unwrap();
+    /// env.add_bridge("open", |env, args| {
+    ///     let path = args.get(0)?.eval(env)?;
+    ///     let src = std::fs::read_to_string(path.is_string()?)
+    ///         .map_err(ErrType::host)?;
+    ///     Ok(src.as_obj())
+    /// });
+    /// ```
+    pub fn host<E: Error + ThreadSafe + 'static>(err: E) -> Self {
+        ErrType::Host(Box::new(err))
+    }
+
+    /// Returns the host application error, if `self`
+    /// carries one of type `E`
+    pub fn host_ref<E: Error + 'static>(&self) -> Option<&E> {
+        match self.root() {
+            ErrType::Host(err) => err.downcast_ref(),
+            _ => None
+        }
+    }
+
+    /// Attaches the location of the failing form
+    /// 
+    /// ## Note
+    /// The innermost location is kept, so an
+    /// already located error is returned as is
+    pub fn at(self, span: &Span) -> Self {
+        match self.span() {
+            Some(_) => self,
+            None => ErrType::Located(Box::new(self), span.clone())
+        }
+    }
+
+    /// Attaches the call stack the error escaped from
+    /// 
+    /// ## Note
+    /// The deepest call stack is kept, so an
+    /// already traced error is returned as is
+    pub fn traced(self, trace: Backtrace) -> Self {
+        match self.backtrace() {
+            Some(_) => self,
+            None => ErrType::Traced(Box::new(self), trace)
+        }
+    }
+
+    /// Returns the location of the failing form, if known
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            ErrType::Located(_, span) => Some(span),
+            ErrType::Traced(err, _) => err.span(),
+            ErrType::Thrown(obj) => obj.span(),
+            _ => None
+        }
+    }
+
+    /// Returns the call stack of the error, if known
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        match self {
+            ErrType::Traced(_, trace) => Some(trace),
+            ErrType::Located(err, _) => err.backtrace(),
+            ErrType::Thrown(obj) => obj.trace(),
+            _ => None
+        }
+    }
+
+    /// Returns whether the error ends the evaluation, having 
+    /// gone past a limit or been cancelled, so `try` can't catch it
+    pub fn is_limit(&self) -> bool {
+        use ErrType::*;
+
+        matches!(self.root(), StepLimit(_) | DepthLimit(_) | AllocLimit(_) | TimeLimit(_) | Cancelled)
+    }
+
+    /// Returns the underlying error, without location or call stack
+    pub fn root(&self) -> &ErrType {
+        match self {
+            ErrType::Located(err, _) => err.root(),
+            ErrType::Traced(err, _) => err.root(),
+            _ => self
+        }
+    }
+
+    /// Returns the underlying error, dropping location and call stack
+    pub fn into_root(self) -> ErrType {
+        match self {
+            ErrType::Located(err, _) => err.into_root(),
+            ErrType::Traced(err, _) => err.into_root(),
+            _ => self
+        }
+    }
+
+    /// Returns the kind of the error as seen by scripts
+    pub fn kind(&self) -> &str {
+        use ErrType::*;
+
+        match self.root() {
+            IoErr(_)            => "io",
+            NonSym(_)           => "undefined-symbol",
+            DupSym(_)           => "duplicate-symbol",
+            NonMod(_)           => "undefined-module",
+            DupMod(_)           => "duplicate-module",
+            CycMod(_)           => "cyclic-require",
+            Params { .. }       => "arity",
+            MisType { .. }      => "type",
+            MisArg { .. }       => "type",
+            MisComp { .. }      => "comparison",
+            MisForm(_)          => "malformed",
+            ErrCast { .. }      => "cast",
+            ErrList             => "list",
+            Overflow(_)         => "overflow",
+            NumOverflow(_)      => "overflow",
+            DivZero             => "div-by-zero",
+            Unbalanced(_)       => "unbalanced",
+            OutOfBound { .. }   => "out-of-bounds",
+            RuntimeAssert(_)    => "assert",
+            ReEntry(_)          => "reentry",
+            Borrowed            => "borrow",
+            Denied(_)           => "capability",
+            StepLimit(_)        => "step-limit",
+            DepthLimit(_)       => "depth-limit",
+            AllocLimit(_)       => "alloc-limit",
+            TimeLimit(_)        => "time-limit",
+            Cancelled           => "cancelled",
+            Host(_)             => "host",
+            Thrown(obj)         => obj.kind(),
+            Located(..) | Traced(..) => unreachable!("root is unwrapped")
+        }
+    }
+
+    /// Converts the error into a script value, 
+    /// keeping its location and call stack
+    pub fn into_obj(self) -> ErrObj {
+        let span = self.span().cloned();
+        let trace = self.backtrace().cloned();
+
+        let obj = match self.into_root() {
+            ErrType::Thrown(obj) => obj,
+            root => ErrObj::new(root.kind(), root.to_string(), Obj::Nil(()))
+        };
+
+        obj.located(span.as_ref(), trace.as_ref())
+    }
+}
+
+impl Error for ErrType {
+    /// Skips the wrapped io or host error, which `Display`
+    /// already prints, so chains don't show it twice
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ErrType::IoErr(err)     => err.source(),
+            ErrType::Host(err)      => err.source(),
+            ErrType::Located(err, _) => err.source(),
+            ErrType::Traced(err, _) => err.source(),
+            _ => None
+        }
+    }
+}
 
 impl Display for ErrType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str("error")
+        use ErrType::*;
+
+        match self {
+            IoErr(err) => write!(f, "io error: {}", err),
+            NonSym(sym) => write!(f, "undefined symbol '{}'", sym),
+            DupSym(sym) => write!(f, "symbol '{}' is already defined", sym),
+            NonMod(module) => write!(f, "undefined module '{}'", module),
+            DupMod(module) => write!(f, "module '{}' is already defined", module),
+            CycMod(cycle) => write!(f, "cyclic require: {}", cycle),
+            Params { expected, found, variadic: false } => {
+                write!(f, "expected {} argument(s), found {}", expected, found)
+            }
+            Params { expected, found, variadic: true } => {
+                write!(f, "expected at least {} argument(s), found {}", expected, found)
+            }
+            MisType { expected, found } => {
+                write!(f, "mismatched types: expected {}, found {}", expected, found)
+            }
+            MisArg { sym, index, expected, found } => {
+                write!(f, "mismatched types: argument {} of '{}' expected {}, found {}", index + 1, sym, expected, found)
+            }
+            MisComp { lhs, rhs } => write!(f, "cannot compare {} with {}", lhs, rhs),
+            MisForm(form) => write!(f, "malformed expression: {}", form),
+            ErrCast { expected, found } => write!(f, "cannot cast {} to {}", found, expected),
+            ErrList => f.write_str("invalid list operation"),
+            Overflow(num) => write!(f, "number '{}' is too large", num),
+            NumOverflow(op) => write!(f, "'{}' overflows", op),
+            DivZero => f.write_str("division by zero"),
+            Unbalanced(brackets) => write!(f, "unbalanced brackets: {}", brackets),
+            OutOfBound { index, len } => {
+                write!(f, "index {} is out of bounds for length {}", index, len)
+            }
+            RuntimeAssert(msg) => write!(f, "assertion failed: {}", msg),
+            ReEntry(sym) => write!(f, "bridge '{}' was called while it is already running", sym),
+            Borrowed => f.write_str("value was modified while it is being read"),
+            Denied(sym) => write!(f, "capability '{}' not granted", sym),
+            StepLimit(max) => write!(f, "evaluation ran more than {} steps", max),
+            DepthLimit(max) => write!(f, "evaluation nested deeper than {} levels", max),
+            AllocLimit(max) => write!(f, "evaluation allocated more than {} list elements and string bytes", max),
+            TimeLimit(time) => write!(f, "evaluation ran longer than {:?}", time),
+            Cancelled => f.write_str("evaluation was cancelled"),
+            Host(err) => write!(f, "{}", err),
+            Located(..) | Traced(..) | Thrown(_) => {
+                match self.root() {
+                    Thrown(obj) if obj.kind() == ErrObj::THROWN => {
+                        write!(f, "uncaught throw: {}", obj.message())?
+                    }
+                    Thrown(obj) => f.write_str(obj.message())?,
+                    root => write!(f, "{}", root)?
+                }
+
+                if let Some(span) = self.span() {
+                    write!(f, "\n  --> {}", span)?;
+                }
+
+                if let Some(trace) = self.backtrace() {
+                    write!(f, "\n  {}", trace)?;
+                }
+
+                Ok(())
+            }
+        }
     }
 }
 
 impl From<io::Error> for ErrType {
-    fn from(_: io::Error) -> Self {
-        ErrType::IoErr
+    fn from(err: io::Error) -> Self {
+        ErrType::IoErr(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::env::Env;
+
+    /// Returns a backtrace of calls to `names`, innermost first
+    fn trace(names: &[&str]) -> Backtrace {
+        let calls = names
+            .iter()
+            .map(|name| CallSite {
+                name: name.to_string(),
+                form: format!("({})", name),
+                span: None
+            })
+            .collect();
+
+        Backtrace { calls }
+    }
+
+    #[test]
+    fn backtrace_folds_recursion() {
+        assert_eq!(trace(&[]).to_string(), "toplevel");
+        assert_eq!(trace(&["F", "G"]).to_string(), "in F <- in G <- toplevel");
+
+        let mut names = vec!["F"; 3998];
+        names.push("G");
+        assert_eq!(trace(&names).to_string(), "in F ×3998 <- in G <- toplevel");
+    }
+
+    #[test]
+    fn backtrace_elides_long_stacks() {
+        let names: Vec<&str> = ["F", "G"].repeat(20);
+        let shown = trace(&names).to_string();
+
+        assert!(shown.starts_with("in F <- in G <- "));
+        assert!(shown.contains("... 24 more <- "));
+        assert!(shown.ends_with("in F <- in G <- toplevel"));
+        assert_eq!(shown.matches("in ").count(), 2 * TRACE_ENDS);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn errors_keep_the_calls_they_unwound() {
+        let env = Env::new().unwrap();
+
+        env.eval_str("(defun f (n) (if (= n 0) (+ 1 \"a\") (+ 1 (f (- n 1)))))").unwrap();
+        env.eval_str("(defun g () (+ 0 (f 3)))").unwrap();
+
+        let err = env.eval_str("(g)").unwrap_err();
+        let trace = err.backtrace().unwrap();
+
+        assert_eq!(trace.calls.len(), 5);
+        assert_eq!(trace.calls[0].form, "(F (- N 1))");
+        assert_eq!(trace.calls[3].form, "(F 3)");
+        assert_eq!(trace.to_string(), "in F ×4 <- in G <- toplevel");
+    }
+
+    #[test]
+    fn host_errors_print_once_in_chains() {
+        let mut env = Env::new().unwrap();
+        env.add_bridge("burn", |_, _| {
+            Err(ErrType::host(io::Error::other("disk on fire")))
+        });
+
+        let err = env.eval_str("(burn)").unwrap_err();
+        assert!(err.host_ref::<io::Error>().is_some());
+
+        let mut chain = err.to_string();
+        let mut source = err.source();
+
+        while let Some(err) = source {
+            chain += &err.to_string();
+            source = err.source();
+        }
+
+        assert_eq!(chain.matches("disk on fire").count(), 1);
+    }
+}
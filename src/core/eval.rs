@@ -1,35 +1,231 @@
+use std::borrow::Cow;
+
 use super::{
     obj::Obj::{*, self},
-    err::Err,
+    err::{Err, ErrType::NonSym},
     env::Env,
-    fun::Callable, 
-    type_id::TypeId,
-    node::NodeIter
+    fun::{Callable, FnNative, Tail}, 
+    scope::Scope,
+    rc_cell::RcCell,
+    node::{Node, NodeIter}
 };
 
+/// Type name reported for list heads that can't be called
+const CALLABLE: &str = "callable";
+
 impl Env {
+    /// Evaluates `obj`
+    /// 
+    /// ## Note
+    /// Forms in tail position are evaluated in a loop instead
+    /// of recursively, so tail calls run in constant Rust stack.
+    /// Tail position is the last form of a function, macro 
+    /// expansion or `let`, `do`, `when` and `unless` body, and 
+    /// the branches of `if`
     pub fn eval(&self, obj: &Obj) -> Err<Obj> {    
-        match obj {      
-            Lst(node) if !node.is_empty() => {       
-                match node.get(0).unwrap() {
-                    Sym(sym) => {
-                        match sym.as_ref() {
-                            Native(f) => f.call(self, node.iter_from(1)),
-                            Bridge(f) => f.call(self, node.iter_from(1)),
-                            Macro(f)  => f.call(self, node.iter_from(1)),
-                            _ => Ok(node.evaled(self)?.as_obj())
-                        }
+        self.restoring(|depth| self.eval_loop(Cow::Borrowed(obj), depth))
+    }
+
+    /// Calls `f` from `form` within `scope`, a frame from
+    /// `FnNative::bind`, evaluating its body to the end
+    pub fn apply_native(&self, f: &FnNative, form: &Obj, scope: Scope) -> Err<Obj> {
+        self.restoring(|depth| {
+            self.push_call(f.name(), form, false);
+            self.set_scope(Some(RcCell::from(scope)));
+
+            let tail = f
+                .run(self)
+                .map_err(|err| self.trace(err))?;
+
+            match tail {
+                Tail::Ret(obj) => Ok(obj),
+                Tail::Eval(obj) => self.eval_loop(Cow::Owned(obj), depth)
+            }
+        })
+    }
+
+    /// Calls the function held by global symbol `sym` with `args`
+    /// 
+    /// ## Example
+    /// ```
+    /// # use jester::{Env, Obj};
+    /// # fn main() -> jester::Err {
+    /// # let env = Env::new()?;
+    /// env.eval_str("(defun add (a b) (+ a b))")?;
+    /// let sum = env.call("add", vec![Obj::from(1), Obj::from(2)])?;
+    /// # assert_eq!(sum, Obj::from(3));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call(&self, sym: &str, args: Vec<Obj>) -> Err<Obj> {
+        let f = self
+            .get(sym)
+            .ok_or_else(|| NonSym(sym.to_string()))?;
+
+        self.apply(&f, args)
+    }
+
+    /// Calls `f` with `args`, already evaluated values
+    pub fn apply(&self, f: &Obj, args: Vec<Obj>) -> Err<Obj> {
+        // args are quoted, as bridges and macros take forms
+        let quote = self.get("quote").unwrap();
+        let form = Lst(std::iter::once(f.clone())
+            .chain(args.iter().map(|arg| match arg {
+                Sym(_) | Lst(_) => Lst(Node::from_iter([quote.clone(), arg.clone()])),
+                _ => arg.clone()
+            }))
+            .collect());
+
+        match f {
+            Native(native) => {
+                let scope = native.bind_values(args)?;
+                self.apply_native(native, &form, scope)
+            }
+            Bridge(_) | Macro(_) => self.eval(&form),
+            _ => Err(f.mis_type(CALLABLE))
+        }
+    }
+
+    /// Expands the macro calls within `form` until none are left
+    /// 
+    /// ## Note
+    /// Quoted forms are left as they are, and any other list 
+    /// is expanded item by item, so a param list naming a 
+    /// macro is expanded as if it were a call
+    pub fn expand(&self, form: &Obj) -> Err<Obj> {
+        let node = match form {
+            Lst(node) if !node.is_empty() => node,
+            _ => return Ok(form.clone())
+        };
+
+        let head = match &*node.get(0)? {
+            Sym(sym) => self.lookup(sym).clone_inner(),
+            _ => Nil(())
+        };
+
+        match head {
+            Macro(f) => return self.expand(&f.expand(self, node.iter_from(1))?),
+            Bridge(f) if f.name() == "quote" => return Ok(form.clone()),
+            _ => ()
+        }
+
+        let mut expanded = node
+            .iter()
+            .map(|item| self.expand(&item.as_ref()).map(RcCell::from))
+            .collect::<Err<Node>>()?;
+
+        if let Some(span) = node.span() {
+            expanded.set_span(span.clone());
+        }
+
+        Ok(Lst(expanded))
+    }
+
+    /// Runs `body` with the call depth on entry, restoring
+    /// the scope and call stack once it returns
+    fn restoring<F>(&self, body: F) -> Err<Obj>
+    where
+        F: FnOnce(usize) -> Err<Obj>
+    {
+        let context = self.context();
+        let (scope, depth) = context.save();
+
+        let res = self.metered(|| body(depth));
+
+        context.restore(scope, depth);
+        self.release_context(&context);
+
+        res
+    }
+
+    fn eval_loop(&self, mut form: Cow<Obj>, depth: usize) -> Err<Obj> {
+        loop {
+            self.step()?;
+
+            let node = match form.as_ref() {
+                Lst(node) if !node.is_empty() => node,
+                Sym(sym) => return Ok(self.lookup(sym).clone_inner()),
+                obj => return Ok(obj.clone())
+            };
+
+            let step = self
+                .eval_list(&form, node, depth)
+                .map_err(|err| {
+                    let err = match node.span() {
+                        Some(span) => err.at(span),
+                        None => err
+                    };
+
+                    match self.call_depth() > depth {
+                        true => self.trace(err),
+                        false => err
                     }
-                    _ => Ok(node.evaled(self)?.as_obj())
-                }     
+                })?;
+
+            match step {
+                Tail::Ret(obj) => return Ok(obj),
+                Tail::Eval(obj) => form = Cow::Owned(obj)
             }
+        }
+    }
+
+    /// Evaluates the non-empty list `node` as a call, erroring
+    /// if its head is not callable
+    /// 
+    /// ## Note
+    /// A list head is evaluated to find the callable, so 
+    /// functions returned by calls can be called in place
+    /// ```text
+    /// ((make-adder 5) 10)
+    /// ```
+    fn eval_list(&self, form: &Obj, node: &Node, depth: usize) -> Err<Tail> {
+        // the head is copied out of its cell, so the
+        // call may set the symbol it was called by
+        let head = match &*node.get(0)? {
+            Sym(sym) => self.lookup(sym).clone_inner(),
+            head @ Lst(_) => head.eval(self)?,
+            head => head.clone()
+        };
 
-            Sym(sym) => Ok(sym.clone_inner()),
-         
-            _ => Ok(obj.clone())
+        match self.call_head(&head, form, node.iter_from(1), depth)? {
+            Some(res) => Ok(res),
+            None => Err(head.mis_type(CALLABLE))
         }
     }
 
+    /// Calls `head` with `args` from `form`, or returns `None`
+    /// if `head` is not callable
+    /// 
+    /// ## Note
+    /// Native and macro calls enter their frame and leave
+    /// their last form to evaluate, replacing the call made 
+    /// by this `Env::eval`, if any, on the call stack
+    fn call_head(&self, head: &Obj, form: &Obj, args: NodeIter, depth: usize) -> Err<Option<Tail>> {
+        let tail = self.call_depth() > depth;
+
+        let res = match head {
+            Native(f) => {
+                let scope = f.bind(self, args)?;
+
+                self.push_call(f.name(), form, tail);
+                self.set_scope(Some(RcCell::from(scope)));
+
+                f.run(self)?
+            }
+
+            Macro(f) => {
+                self.push_call(f.name(), form, tail);
+                Tail::Eval(f.expand(self, args)?)
+            }
+
+            Bridge(f) => f.call_tail(self, args)?,
+
+            _ => return Ok(None)
+        };
+
+        Ok(Some(res))
+    }
+
     pub fn eval_args<const L: usize>(&self, indices: [usize; L], args: NodeIter) -> Err<[Obj; L]> {
         const NIL: Obj = Obj::Nil(());
         let mut arr = [NIL; L];
@@ -40,4 +236,52 @@ impl Env {
 
         Ok(arr)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{
+        builder::EnvBuilder,
+        limit::Limits
+    };
+
+    const SETUP: &str = "
+        (defun sum-to (n acc) (if (= n 0) acc (sum-to (- n 1) (+ acc n))))
+        (defun even? (n) (if (= n 0) True (odd? (- n 1))))
+        (defun odd? (n) (if (= n 0) False (even? (- n 1))))
+        (defun down (n) (let (m (- n 1)) (if (= m 0) 'done (down m))))
+        (defun depth (n) (if (= n 0) 0 (+ 1 (depth (- n 1)))))
+    ";
+
+    /// Evaluates `src` after `SETUP` by both evaluators, nesting at most
+    /// 100 levels, returning its value as printed or the kind of error
+    fn eval(src: &str) -> [String; 2] {
+        [false, true].map(|interpreted| {
+            let env = EnvBuilder::new()
+                .interpreted(interpreted)
+                .limits(Limits { depth: Some(100), ..Limits::default() })
+                .build()
+                .unwrap();
+
+            env.eval_str(SETUP).unwrap();
+
+            match env.eval_str(src) {
+                Ok(obj) => obj.display(&env),
+                Err(err) => format!("error: {}", err.kind())
+            }
+        })
+    }
+
+    #[test]
+    fn tail_calls_run_past_the_depth_limit() {
+        assert_eq!(eval("(sum-to 10000 0)"), ["50005000", "50005000"]);
+        assert_eq!(eval("(even? 5001)"), ["false", "false"]);
+        assert_eq!(eval("(down 5000)"), ["DONE", "DONE"]);
+    }
+
+    #[test]
+    fn calls_out_of_tail_position_nest() {
+        assert_eq!(eval("(depth 20)"), ["20", "20"]);
+        assert_eq!(eval("(depth 1000)"), ["error: depth-limit", "error: depth-limit"]);
+    }
+}
@@ -1,62 +1,202 @@
 use super::{
     id::Id, 
+    vm::{self, Chunk},
     env::Env, 
     obj::Obj,
-    type_id::TypeId,
+    scope::Scope,
+    rc_cell::RcCell,
+    sync::{Shared, Lock, Once, ThreadSafe},
+    type_id::{TypeId, FromObj, IntoObj},
     err::{Err, ErrType::*},
     node::{Node, NodeIter}, 
+    gc::{Trace, Tracer}
 };
 
-pub type Bridge = fn(&Env, NodeIter) -> Err<Obj>;
+/// Signature of a function defined by the host
+#[cfg(not(feature = "sync"))]
+type BridgeFn = dyn Fn(&Env, NodeIter) -> Err<Obj>;
+
+/// Signature of a function defined by the host
+#[cfg(feature = "sync")]
+type BridgeFn = dyn Fn(&Env, NodeIter) -> Err<Obj> + Send + Sync;
+
+/// Signature of a bridge that mutates the state it captures
+#[cfg(not(feature = "sync"))]
+type BridgeMutFn = dyn FnMut(&Env, NodeIter) -> Err<Obj>;
+
+/// Signature of a bridge that mutates the state it captures
+#[cfg(feature = "sync")]
+type BridgeMutFn = dyn FnMut(&Env, NodeIter) -> Err<Obj> + Send + Sync;
+
+/// A function defined by the host, which may capture state
+pub type Bridge = Box<BridgeFn>;
+
+/// A bridge that mutates the state it captures
+pub type BridgeMut = Box<BridgeMutFn>;
+
+/// A bridge whose result may be a form in tail position
+pub type TailBridge = fn(&Env, NodeIter) -> Err<Tail>;
+
+/// A Rust function taking `Args`, that can be registered
+/// as a bridge evaluating and converting its args
+/// 
+/// ## Note
+/// Implemented for functions of up to 8 params that
+/// are `FromObj`, returning a value that is `IntoObj`
+pub trait HostFn<Args>: ThreadSafe + 'static {
+    /// Calls the function with the evaluated `args`,
+    /// where `sym` names it in errors
+    fn call_host(&self, sym: &str, env: &Env, args: NodeIter) -> Err<Obj>;
+}
+
+/// Evaluates arg `index` and converts it into `T`, naming the
+/// function `sym` and the arg if its type is wrong or out of range
+fn host_arg<T: FromObj>(sym: &str, index: usize, env: &Env, args: &NodeIter) -> Err<T> {
+    let obj = args.get(index)?.eval(env)?;
+
+    let mis_arg = |expected, found| MisArg {
+        sym: sym.to_string(),
+        index,
+        expected,
+        found
+    };
+
+    T::from_obj(obj).map_err(|err| match err {
+        MisType { expected, found } | ErrCast { expected, found } => mis_arg(expected, found),
+        Overflow(num) => mis_arg(std::any::type_name::<T>().to_string(), num),
+        err => err
+    })
+}
+
+/// Implements `HostFn` for functions of params `$arg: $ty`
+macro_rules! host_fn {
+    ($($arg:ident: $ty:ident),*) => {
+        impl<Fun, Ret, $($ty),*> HostFn<($($ty,)*)> for Fun
+        where
+            Fun: Fn($($ty),*) -> Ret + ThreadSafe + 'static,
+            Ret: IntoObj,
+            $($ty: FromObj),*
+        {
+            #[allow(unused_variables, unused_mut, unused_assignments)]
+            fn call_host(&self, sym: &str, env: &Env, args: NodeIter) -> Err<Obj> {
+                const ARITY: usize = <[&str]>::len(&[$(stringify!($arg)),*]);
+
+                if args.len() != ARITY {
+                    return Err(Params {
+                        expected: ARITY,
+                        found: args.len(),
+                        variadic: false
+                    })
+                }
+
+                let mut index = 0;
+
+                $(
+                    let $arg = host_arg::<$ty>(sym, index, env, &args)?;
+                    index += 1;
+                )*
+
+                self($($arg),*).into_obj()
+            }
+        }
+    };
+}
+
+host_fn!();
+host_fn!(a: A);
+host_fn!(a: A, b: B);
+host_fn!(a: A, b: B, c: C);
+host_fn!(a: A, b: B, c: C, d: D);
+host_fn!(a: A, b: B, c: C, d: D, e: E);
+host_fn!(a: A, b: B, c: C, d: D, e: E, f: F);
+host_fn!(a: A, b: B, c: C, d: D, e: E, f: F, g: G);
+host_fn!(a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H);
+
+/// Result of a call that can end in tail position
+pub enum Tail {
+    /// The final value of the call
+    Ret(Obj),
+    /// A form left to evaluate in place of the call
+    /// 
+    /// ## Note
+    /// The form is evaluated in the scope the call left
+    /// `Env` in, so `let` can continue within its frame
+    Eval(Obj)
+}
 
 pub trait Callable {
     fn call(&self, env: &Env, args: NodeIter) -> Err<Obj>;
     fn name(&self) -> &String;
 }
 
+/// Binds each of `params` to its value from `args` in a new frame
+/// on top of `scope`, folding the remaining args into a list bound
+/// to the last param if `fold` is set
+/// 
+/// ## Note
+/// `found` is the number of args, which are only produced
+/// once the arity is checked
+fn bind_params<I>(params: &Node, fold: bool, scope: &Option<RcCell<Scope>>, found: usize, mut args: I) -> Err<Scope> 
+where
+    I: Iterator<Item = Err<Obj>>
+{
+    let delta = found.checked_sub(params.len());
+
+    if let (None, _) | (Some(1..), false) = (delta, fold) {
+        return Err(Params { 
+            expected: params.len(), 
+            found, 
+            variadic: fold 
+        })
+    }
+
+    let mut frame = Scope::new(scope.clone());
+
+    for (i, param) in params.iter().enumerate() {
+        let val = if fold && i + 1 == params.len() {
+            args
+                .by_ref()
+                .collect::<Err<Node>>()?
+                .as_obj()
+        } 
+        else {
+            args.next().unwrap_or_else(|| Err(OutOfBound { index: i, len: found }))?
+        };
+
+        frame.bind(param.as_ref().is_symbol()?, val)?;
+    }
+
+    Ok(frame)
+}
+
+/// A function defined in `Jester-rs`
+/// 
+/// ## Note
+/// `scope` is the lexical scope the function was defined in,
+/// each call binds its params in a new frame on top of it.
+/// The body is compiled to a `Chunk` on its first call, 
+/// shared by every clone of the function
 #[derive(Clone)]
 pub struct FnNative {
     name: String,
-    params: Node,
-    body: Node,
+    params: Shared<Node>,
+    body: Shared<Node>,
     fold: bool,
+    scope: Option<RcCell<Scope>>,
+    code: Shared<Once<Chunk>>,
     id: Id
 }
 
 impl Callable for FnNative {
     fn call(&self, env: &Env, args: NodeIter) -> Err<Obj> {
-        let delta = args.len().checked_sub(self.params.len());
-
-        match (delta, self.fold) {
-            (None, _) => Err(Params),
-
-            (Some(n), false) if n > 0 => Err(Params),
-
-            (Some(n), true) => {
-                let fold = args
-                    .skip(args.len() - n - 1)
-                    .map(|obj| env.eval(obj.as_ref()))
-                    .collect::<Err<Node>>()?
-                    .as_obj();
-
-                let node = vec![fold.into()];
-
-                let fold_args = args
-                    .take(args.len() - n - 1)
-                    .chain(node.iter());
-
-                self
-                    .body
-                    .iter()
-                    .progn_scoped(env, self.params.iter(), fold_args)
-            }
+        let scope = self.bind(env, args)?;
 
-            (Some(_), false) => {
-                self.body
-                    .iter()
-                    .progn_scoped(env, self.params.iter(), args)
+        env.in_scope(RcCell::from(scope), || {
+            match self.run(env)? {
+                Tail::Ret(obj) => Ok(obj),
+                Tail::Eval(obj) => env.eval(&obj)
             }
-        }
+        })
     }
 
     fn name(&self) -> &String {
@@ -64,6 +204,18 @@ impl Callable for FnNative {
     }
 }
 
+impl Trace for FnNative {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.rc(&self.params);
+        tracer.rc(&self.body);
+        tracer.rc(&self.code);
+
+        if let Some(scope) = &self.scope {
+            tracer.cell(scope);
+        }
+    }
+}
+
 impl PartialEq for FnNative {
     fn eq(&self, other: &Self) -> bool {
         self.id == other.id
@@ -71,12 +223,14 @@ impl PartialEq for FnNative {
 }
 
 impl FnNative {
-    pub fn new(name: String, params: Node, body: Node, fold: bool) -> Self {
+    pub fn new(name: String, params: Node, body: Node, fold: bool, scope: Option<RcCell<Scope>>) -> Self {
         Self {
             name,
-            params,
-            body,
+            params: Shared::new(params),
+            body: Shared::new(body),
             fold,
+            scope,
+            code: Shared::default(),
             id: Id::new()
         }
     }
@@ -84,26 +238,158 @@ impl FnNative {
     pub fn params(&self) -> &Node {
         &self.params
     }
+
+    pub fn body(&self) -> &Node {
+        &self.body
+    }
+
+    /// Returns the docstring of the function, see `doc_of`
+    pub fn doc(&self) -> Option<String> {
+        doc_of(&self.body)
+    }
+
+    /// Returns a new frame binding the params to the evaluated `args`
+    pub fn bind(&self, env: &Env, args: NodeIter) -> Err<Scope> {
+        let found = args.len();
+
+        bind_params(&self.params, self.fold, &self.scope, found, args.map(|obj| {
+            env.eval(&obj.as_ref())
+        }))
+    }
+
+    /// Returns a new frame binding the params to `args`
+    pub fn bind_values(&self, args: Vec<Obj>) -> Err<Scope> {
+        let found = args.len();
+        bind_params(&self.params, self.fold, &self.scope, found, args.into_iter().map(Ok))
+    }
+
+    /// Returns the compiled body, compiling it on the first call
+    /// 
+    /// ## Note
+    /// Compiling may expand macros, which can call the function
+    /// being compiled, so the first finished compilation wins
+    pub fn chunk(&self, env: &Env) -> &Chunk {
+        if self.code.get().is_none() {
+            let chunk = Chunk::compile(env, &self.params, &self.body, &self.scope);
+            let _ = self.code.set(chunk);
+        }
+
+        self.code
+            .get()
+            .unwrap()
+    }
+
+    /// Runs the body in the current scope of `env`, which
+    /// must be a frame from `FnNative::bind`, leaving the 
+    /// last form in tail position
+    pub fn run(&self, env: &Env) -> Err<Tail> {
+        if env.interpreted() || self.body.is_empty() {
+            return self.body
+                .iter()
+                .progn_tail(env)
+        }
+
+        vm::run(env, self)
+    }
+}
+
+/// Body of a bridge, shared by every clone of it
+#[derive(Clone)]
+enum BridgeBody {
+    Ret(Shared<BridgeFn>),
+    Mut(Shared<Lock<BridgeMut>>),
+    Tail(TailBridge),
+    /// stands in for a capability the host didn't grant
+    Denied
 }
 
 #[derive(Clone)]
 pub struct FnBridge {
     name: String,
-    body: Bridge
+    body: BridgeBody
 }
 
 impl FnBridge {
     pub fn new(name: String, body: Bridge) -> Self {
         FnBridge { 
             name,
-            body
+            body: BridgeBody::Ret(Shared::from(body))
+        }
+    }
+
+    pub fn new_mut(name: String, body: BridgeMut) -> Self {
+        FnBridge { 
+            name,
+            body: BridgeBody::Mut(Shared::new(Lock::new(body)))
+        }
+    }
+
+    pub fn new_tail(name: String, body: TailBridge) -> Self {
+        FnBridge { 
+            name,
+            body: BridgeBody::Tail(body)
+        }
+    }
+
+    /// Makes a bridge failing with a `Denied` error, 
+    /// in place of the capability `name`
+    pub fn denied(name: String) -> Self {
+        FnBridge { 
+            name,
+            body: BridgeBody::Denied
+        }
+    }
+
+    pub fn is_denied(&self) -> bool {
+        matches!(self.body, BridgeBody::Denied)
+    }
+
+    /// Checks if `self` and `other` run the same host function,
+    /// being clones of one bridge or stand-ins for one capability
+    pub fn same_def(&self, other: &Self) -> bool {
+        match (&self.body, &other.body) {
+            (BridgeBody::Ret(lhs), BridgeBody::Ret(rhs))   => Shared::ptr_eq(lhs, rhs),
+            (BridgeBody::Mut(lhs), BridgeBody::Mut(rhs))   => Shared::ptr_eq(lhs, rhs),
+            (BridgeBody::Tail(lhs), BridgeBody::Tail(rhs)) => std::ptr::fn_addr_eq(*lhs, *rhs),
+            (BridgeBody::Denied, BridgeBody::Denied)       => self.name == other.name,
+            _ => false
+        }
+    }
+
+    /// Calls the bridge, leaving a form in tail 
+    /// position unevaluated
+    /// 
+    /// ## Note
+    /// A mutable bridge can't run while it is already running,
+    /// so calling it again from its own args is an error
+    pub fn call_tail(&self, env: &Env, args: NodeIter) -> Err<Tail> {
+        match &self.body {
+            BridgeBody::Ret(body)  => body(env, args).map(Tail::Ret),
+            BridgeBody::Tail(body) => body(env, args),
+            BridgeBody::Denied     => Err(Denied(self.name.clone())),
+            BridgeBody::Mut(body)  => {
+                let mut body = body
+                    .try_write()
+                    .ok_or_else(|| ReEntry(self.name.clone()))?;
+
+                body(env, args).map(Tail::Ret)
+            }
         }
     }
 }
 
 impl Callable for FnBridge {
     fn call(&self, env: &Env, args: NodeIter) -> Err<Obj> {
-        (self.body)(env, args)
+        let scope = env.scope();
+
+        let res = match self.call_tail(env, args) {
+            Ok(Tail::Ret(obj))  => Ok(obj),
+            Ok(Tail::Eval(obj)) => env.eval(&obj),
+            Err(err) => Err(err)
+        };
+
+        env.set_scope(scope);
+        res
     }
 
     fn name(&self) -> &String {
@@ -111,12 +397,40 @@ impl Callable for FnBridge {
     }
 }
 
+/// Returns the string a body begins with, if more forms follow it
+/// 
+/// ## Example
+/// ```text
+/// (defun sq (x)
+///     "returns x times itself"
+///     (* x x))
+/// ```
+fn doc_of(body: &Node) -> Option<String> {
+    if body.len() < 2 {
+        return None
+    }
+
+    match &*body.get(0).ok()? {
+        Obj::Str(doc) => Some(doc.clone()),
+        _ => None
+    }
+}
+
+/// A macro defined in `Jester-rs`
+/// 
+/// ## Note
+/// Like `FnNative`, each expansion binds its params in a new 
+/// frame on top of `scope`, the scope the macro was defined in.
+/// A pure macro only builds its expansion from its args, so 
+/// compiled bodies may expand its calls once, ahead of time
 #[derive(Clone)]
 pub struct FnMacro {
     name: String,
-    params: Node,
-    body: Node,
+    params: Shared<Node>,
+    body: Shared<Node>,
     fold: bool,
+    pure: bool,
+    scope: Option<RcCell<Scope>>,
     id: Id
 }
 
@@ -132,6 +446,17 @@ impl Callable for FnMacro {
     }
 }
 
+impl Trace for FnMacro {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.rc(&self.params);
+        tracer.rc(&self.body);
+
+        if let Some(scope) = &self.scope {
+            tracer.cell(scope);
+        }
+    }
+}
+
 impl PartialEq for FnMacro {
     fn eq(&self, other: &Self) -> bool {
         self.id == other.id
@@ -139,12 +464,14 @@ impl PartialEq for FnMacro {
 }
 
 impl FnMacro {
-    pub fn new(name: String, params: Node, body: Node, fold: bool) -> Self {
+    pub fn new(name: String, params: Node, body: Node, fold: bool, scope: Option<RcCell<Scope>>) -> Self {
         Self {
             name,
-            params,
-            body,
+            params: Shared::new(params),
+            body: Shared::new(body),
             fold,
+            pure: false,
+            scope,
             id: Id::new()
         }
     }
@@ -153,38 +480,134 @@ impl FnMacro {
         &self.params
     }
 
+    pub fn body(&self) -> &Node {
+        &self.body
+    }
+
+    pub fn is_pure(&self) -> bool {
+        self.pure
+    }
+
+    /// Marks the macro as pure, see `FnMacro`
+    pub(crate) fn set_pure(&mut self) {
+        self.pure = true;
+    }
+
+    /// Returns the docstring of the macro, see `doc_of`
+    pub fn doc(&self) -> Option<String> {
+        doc_of(&self.body)
+    }
+
+    /// Returns whether `self` and `other` are clones 
+    /// of the same definition
+    /// 
+    /// ## Note
+    /// Clones are given new `Id`s, so this compares
+    /// the shared body instead
+    pub fn same_def(&self, other: &Self) -> bool {
+        Shared::ptr_eq(&self.body, &other.body)
+    }
+
+    /// Returns the expansion of the macro for `args`
     pub fn expand(&self, env: &Env, args: NodeIter) -> Err<Obj> {
-        let delta = args.len().checked_sub(self.params.len());
+        let found = args.len();
 
-        match (delta, self.fold) {
-            (None, _) => Err(Params),
+        let scope = bind_params(&self.params, self.fold, &self.scope, found, args.map(|obj| {
+            Ok(obj.clone_inner())
+        }))?;
 
-            (Some(n), false) if n > 0 => Err(Params),
+        self.body
+            .iter()
+            .progn_scoped(env, scope)
+    }
+}
 
-            (Some(n), true) => {
-                let fold = args
-                    .skip(args.len() - n - 1)
-                    .cloned()
-                    .collect::<Node>()
-                    .as_obj();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                let node = vec![fold.into()];
+    fn eval(src: &str) -> String {
+        let env = Env::new().unwrap();
+        let obj = env.eval_str(src).unwrap();
 
-                let fold_args = args
-                    .take(args.len() - n - 1)
-                    .chain(node.iter());
+        obj.display(&env)
+    }
 
-                self
-                    .body
-                    .iter()
-                    .progn_macro(env, self.params.iter(), fold_args)
-            }
+    #[test]
+    fn params_leave_globals_alone() {
+        let src = "
+            (set x 10)
+            (defmacro twice (x) '(list ,x ,x))
+            (defun boom (x) (/ x 0))
+            (list (twice 3) (try (boom 5) (catch e x)) x)";
 
-            (Some(_), false) => {
-                self.body
-                    .iter()
-                    .progn_macro(env, self.params.iter(), args)
-            }
-        }
+        assert_eq!(eval(src), "((3 3) 10 10)");
+    }
+
+    #[test]
+    fn loops_in_calls_keep_their_own_vars() {
+        let src = "
+            (set i 99)
+            (defun count (n) (let (acc ()) (for i in 0 to n (append i acc)) acc))
+            (let (r ()) (for i in 0 to 3 (append (list i (count i)) r)) (list r i))";
+
+        assert_eq!(eval(src), "(((0 ()) (1 (0)) (2 (0 1))) 99)");
+    }
+
+    #[test]
+    fn bridges_keep_their_state() {
+        use std::sync::{Arc, atomic::{AtomicI64, Ordering}};
+
+        let mut env = Env::new().unwrap();
+
+        let total = Arc::new(AtomicI64::new(0));
+        let sum = total.clone();
+
+        env.add_bridge("add!", move |env, args| {
+            let x = args.get(0)?.eval(env)?.is_int()?;
+            Ok(Obj::from(sum.fetch_add(x as i64, Ordering::SeqCst) + x as i64))
+        });
+
+        let mut count: i64 = 0;
+        env.add_bridge_mut("tick", move |_, _| {
+            count += 1;
+            Ok(Obj::from(count))
+        });
+
+        assert_eq!(env.eval_str("(add! 2) (add! 3)").unwrap(), Obj::from(5_i64));
+        assert_eq!(total.load(Ordering::SeqCst), 5);
+        assert_eq!(env.eval_str("(tick) (tick) (tick)").unwrap(), Obj::from(3_i64));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn mut_bridges_refuse_reentry() {
+        let mut env = Env::new().unwrap();
+
+        env.add_bridge_mut("again", |env, args| {
+            args.get(0)?.eval(env)
+        });
+
+        assert_eq!(env.eval_str("(again 1)").unwrap(), Obj::from(1));
+
+        let err = env.eval_str("(again (again 1))").unwrap_err();
+        assert_eq!(err.kind(), "reentry");
+
+        // the failed call leaves the bridge free
+        assert_eq!(env.eval_str("(again 3)").unwrap(), Obj::from(3));
+    }
+
+    #[test]
+    fn host_args_out_of_range_name_the_arg() {
+        let mut env = Env::new().unwrap();
+        env.register("ri", |a: i32| a);
+
+        assert_eq!(env.eval_str("(ri 7)").unwrap(), Obj::from(7));
+
+        let err = env.eval_str("(ri 99999999999)").unwrap_err();
+        assert_eq!(err.kind(), "type");
+        assert_eq!(
+            err.to_string().lines().next().unwrap(),
+            "mismatched types: argument 1 of 'ri' expected i32, found 99999999999"
+        );
+    }
+}
@@ -0,0 +1,414 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet, VecDeque}
+};
+
+use super::{
+    env::Env,
+    obj::Obj,
+    scope::Scope,
+    rc_cell::RcCell,
+    limit,
+    sync::{Shared, Weak, Lock}
+};
+
+/// Number of live objects, as returned by `Env::live_objects`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LiveObjects {
+    /// cells holding a value, on this thread
+    pub cells: usize,
+    /// lexical frames, on this thread
+    pub scopes: usize,
+    /// named symbols of the `Env`
+    pub symbols: usize,
+    /// symbols made by `gen_sym` still referred to
+    pub gensyms: usize
+}
+
+/// Fewest tracked cells before collecting automatically
+const MIN_THRESHOLD: usize = 10_000;
+
+thread_local! {
+    /// Every `RcCell<Obj>` made on this thread
+    static CELLS: RefCell<Vec<Weak<Lock<Obj>>>> = const { RefCell::new(Vec::new()) };
+    /// Every `RcCell<Scope>` made on this thread
+    static SCOPES: RefCell<Vec<Weak<Lock<Scope>>>> = const { RefCell::new(Vec::new()) };
+    /// Number of tracked cells that triggers the next collection
+    static THRESHOLD: Cell<usize> = const { Cell::new(MIN_THRESHOLD) };
+}
+
+/// A value the collector can see into
+///
+/// ## Note
+/// `trace` must report every `Rc` held by the value, each
+/// as many times as it is held, since the collector counts
+/// them against the `Rc`'s strong count. Whatever isn't
+/// reported (such as the captures of a bridge) is taken
+/// to be held from outside, keeping it alive
+pub trait Trace {
+    /// Reports each `Rc` held by `self` to `tracer`
+    fn trace(&self, tracer: &mut Tracer);
+
+    /// Drops what `self` holds, breaking the cycles it is in
+    fn clear(&self) {}
+}
+
+/// Types whose `RcCell`s are tracked by the collector
+pub trait Track: Sized {
+    fn track(raw: &Shared<Lock<Self>>);
+}
+
+impl Track for Obj {
+    fn track(raw: &Shared<Lock<Self>>) {
+        limit::alloc(1);
+        CELLS.with(|cells| cells.borrow_mut().push(Shared::downgrade(raw)));
+    }
+}
+
+impl Track for Scope {
+    fn track(raw: &Shared<Lock<Self>>) {
+        SCOPES.with(|scopes| scopes.borrow_mut().push(Shared::downgrade(raw)));
+    }
+}
+
+impl Trace for Lock<Obj> {
+    fn trace(&self, tracer: &mut Tracer) {
+        match self.try_read() {
+            Some(obj) => obj.trace(tracer),
+            None => tracer.opaque()
+        }
+    }
+
+    fn clear(&self) {
+        // dropped once the cell is released
+        let obj = match self.try_write() {
+            Some(mut obj) => std::mem::replace(&mut *obj, Obj::Nil(())),
+            None => return
+        };
+
+        drop(obj);
+    }
+}
+
+impl Trace for Lock<Scope> {
+    fn trace(&self, tracer: &mut Tracer) {
+        match self.try_read() {
+            Some(scope) => scope.trace(tracer),
+            None => tracer.opaque()
+        }
+    }
+
+    fn clear(&self) {
+        let scope = match self.try_write() {
+            Some(mut scope) => std::mem::take(&mut *scope),
+            None => return
+        };
+
+        drop(scope);
+    }
+}
+
+/// Identity of an `Rc` allocation
+type Addr = *const ();
+
+/// An `Rc` found by the collector
+struct Entry {
+    weak: Weak<dyn Trace>,
+    /// strong count of the `Rc`
+    strong: usize,
+    /// strong references from other found `Rc`s
+    internal: usize,
+    /// found `Rc`s this one holds
+    edges: Vec<Addr>,
+    /// whether its content couldn't be traced
+    opaque: bool
+}
+
+/// Finds the graph of `Rc`s held by tracked cells
+///
+/// ## Note
+/// An `Rc` holding more strong references than the found
+/// `Rc`s account for is held from outside the graph, by
+/// `Env` or the host. Whatever it can reach is live, and
+/// the rest are unreachable cycles
+#[derive(Default)]
+pub struct Tracer {
+    entries: HashMap<Addr, Entry>,
+    pending: Vec<Addr>,
+    current: Option<Addr>
+}
+
+impl Tracer {
+    /// Reports `rc`, held by the value being traced
+    pub fn rc<T: Trace + 'static>(&mut self, rc: &Shared<T>) {
+        let addr = Shared::as_ptr(rc) as Addr;
+
+        if let Some(from) = self.current {
+            self.entries
+                .get_mut(&from)
+                .unwrap()
+                .edges
+                .push(addr);
+        }
+
+        match self.entries.get_mut(&addr) {
+            Some(entry) => entry.internal += 1,
+            None => {
+                let weak: Weak<T> = Shared::downgrade(rc);
+                self.insert(addr, weak, Shared::strong_count(rc), 1);
+            }
+        }
+    }
+
+    /// Reports `cell`, held by the value being traced
+    pub fn cell<T: 'static>(&mut self, cell: &RcCell<T>)
+    where
+        Lock<T>: Trace
+    {
+        self.rc(cell.as_raw())
+    }
+
+    /// Marks the value being traced as live, as it couldn't be seen into
+    pub fn opaque(&mut self) {
+        if let Some(entry) = self.current.and_then(|addr| self.entries.get_mut(&addr)) {
+            entry.opaque = true;
+        }
+    }
+
+    fn insert(&mut self, addr: Addr, weak: Weak<dyn Trace>, strong: usize, internal: usize) {
+        self.entries.insert(addr, Entry {
+            weak,
+            strong,
+            internal,
+            edges: Vec::new(),
+            opaque: false
+        });
+
+        self.pending.push(addr);
+    }
+
+    /// Adds a tracked `Rc` to the graph, if it is alive and not yet found
+    fn seed(&mut self, weak: Weak<dyn Trace>) {
+        let addr = weak.as_ptr() as Addr;
+
+        if weak.strong_count() > 0 && !self.entries.contains_key(&addr) {
+            let strong = weak.strong_count();
+            self.insert(addr, weak, strong, 0);
+        }
+    }
+
+    /// Traces every pending `Rc` until the graph is complete
+    fn run(&mut self) {
+        while let Some(addr) = self.pending.pop() {
+            let Some(rc) = self.entries[&addr].weak.upgrade() else {
+                continue
+            };
+
+            self.current = Some(addr);
+            rc.trace(self);
+            self.current = None;
+        }
+    }
+
+    /// Returns the `Rc`s no outside reference can reach
+    fn unreachable(&self) -> Vec<Shared<dyn Trace>> {
+        let mut live: VecDeque<Addr> = self.entries
+            .iter()
+            .filter(|(_, entry)| entry.opaque || entry.strong > entry.internal)
+            .map(|(addr, _)| *addr)
+            .collect();
+
+        let mut seen: HashSet<Addr> = live
+            .iter()
+            .copied()
+            .collect();
+
+        while let Some(addr) = live.pop_front() {
+            for edge in &self.entries[&addr].edges {
+                if seen.insert(*edge) {
+                    live.push_back(*edge);
+                }
+            }
+        }
+
+        self.entries
+            .iter()
+            .filter(|(addr, _)| !seen.contains(addr))
+            .filter_map(|(_, entry)| entry.weak.upgrade())
+            .collect()
+    }
+}
+
+/// Frees the cells and scopes on this thread that are
+/// only reachable from each other, returning how many
+pub fn collect() -> usize {
+    let mut tracer = Tracer::default();
+
+    CELLS.with(|cells| {
+        let mut cells = cells.borrow_mut();
+        cells.retain(|weak| weak.strong_count() > 0);
+        cells.iter().for_each(|weak| tracer.seed(weak.clone()));
+    });
+
+    SCOPES.with(|scopes| {
+        let mut scopes = scopes.borrow_mut();
+        scopes.retain(|weak| weak.strong_count() > 0);
+        scopes.iter().for_each(|weak| tracer.seed(weak.clone()));
+    });
+
+    tracer.run();
+
+    let garbage = tracer.unreachable();
+    let freed = garbage.len();
+
+    // each is kept alive until all are cleared, so clearing
+    // one can't free another while it is being cleared
+    for rc in &garbage {
+        rc.clear();
+    }
+
+    drop(garbage);
+
+    let (cells, scopes) = live();
+    THRESHOLD.with(|threshold| threshold.set(MIN_THRESHOLD.max(2 * (cells + scopes))));
+
+    freed
+}
+
+/// Returns whether enough cells were made since the
+/// last collection to collect again
+pub fn should_collect() -> bool {
+    let tracked = CELLS.with(|cells| cells.borrow().len())
+        + SCOPES.with(|scopes| scopes.borrow().len());
+
+    tracked >= THRESHOLD.with(Cell::get)
+}
+
+/// Returns the number of `weaks` still alive
+fn count_live<T: ?Sized>(weaks: &[Weak<T>]) -> usize {
+    weaks
+        .iter()
+        .filter(|weak| weak.strong_count() > 0)
+        .count()
+}
+
+/// Returns the number of live cells and scopes on this thread
+pub fn live() -> (usize, usize) {
+    (
+        CELLS.with(|cells| count_live(&cells.borrow())),
+        SCOPES.with(|scopes| count_live(&scopes.borrow()))
+    )
+}
+
+impl Env {
+    /// Frees the symbols and values nothing can reach
+    /// anymore, returning how many were freed
+    /// 
+    /// ## Note
+    /// - named symbols holding `nil` that no code or value
+    ///   refers to are dropped, as reading them again makes
+    ///   an identical symbol
+    /// - cells and frames only reachable from each other, such
+    ///   as a closure bound in the frame it captured, are freed
+    ///   by clearing them, which drops the cycle
+    /// 
+    /// Values held by the host count as reachable, so this 
+    /// is safe to call at any point between evaluations. It
+    /// also runs between top-level forms once enough cells 
+    /// were made since the last collection
+    /// 
+    /// With the `sync` feature it never runs by itself, and must
+    /// only be called while no other thread is evaluating, as a
+    /// value moved by another thread could be taken for garbage
+    pub fn collect(&self) -> usize {
+        let mut freed = 0;
+
+        self.symbols
+            .write()
+            .retain(|_, cell| {
+                let unused = Shared::strong_count(cell.as_raw()) == 1 
+                    && matches!(*cell.as_ref(), Obj::Nil(_));
+
+                freed += unused as usize;
+                !unused
+            });
+
+        self.gensyms
+            .write()
+            .retain(|_, weak| weak.strong_count() > 0);
+
+        freed + collect()
+    }
+
+    /// Collects if enough cells were made since
+    /// the last collection and no form is running
+    pub(crate) fn collect_if_due(&self) {
+        if !cfg!(feature = "sync") && should_collect() && self.at_top_level() {
+            self.collect();
+        }
+    }
+
+    /// Returns the number of live objects
+    pub fn live_objects(&self) -> LiveObjects {
+        let (cells, scopes) = live();
+
+        LiveObjects {
+            cells,
+            scopes,
+            symbols: self.symbols.read().len(),
+            gensyms: self.gensyms
+                .read()
+                .values()
+                .filter(|weak| weak.strong_count() > 0)
+                .count()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::env::Env;
+
+    #[test]
+    fn list_cycles_are_collected() {
+        let env = Env::new().unwrap();
+        env.collect();
+        let before = env.live_objects();
+
+        env.eval_str("(set a (list 1 2)) (append a a) (set a nil)").unwrap();
+        assert!(env.collect() > 0);
+        assert_eq!(env.live_objects().cells, before.cells);
+    }
+
+    #[test]
+    fn gen_syms_are_collected() {
+        let env = Env::new().unwrap();
+        env.collect();
+        let before = env.live_objects();
+
+        env.eval_str("(set keep (gen-sym)) (for i in 0 to 50 (gen-sym))").unwrap();
+        env.collect();
+
+        let after = env.live_objects();
+        // only `keep` and the gen-sym it holds are left
+        assert_eq!(after.gensyms, before.gensyms + 1);
+        assert_eq!(after.symbols, before.symbols + 1);
+    }
+
+    #[test]
+    fn host_values_are_kept() {
+        let env = Env::new().unwrap();
+        env.collect();
+        let before = env.live_objects();
+
+        let obj = env.eval_str("(let (l (list 1 2)) (append l l) l)").unwrap();
+        env.collect();
+
+        assert_eq!(obj.is_node().unwrap().len(), 3);
+        assert!(env.live_objects().cells > before.cells);
+
+        drop(obj);
+        env.collect();
+        assert_eq!(env.live_objects().cells, before.cells);
+    }
+}
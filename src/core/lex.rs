@@ -1,4 +1,8 @@
-use std::fmt::Debug;
+use std::{
+    fmt::{Debug, Display}
+};
+
+use super::sync::Shared;
 
 
 /// `Jester-rs` delimeters
@@ -11,35 +15,63 @@ pub(crate) const CONTROLS: [char; 5] = [
 ];
     
 /// `Jester-rs` operators
-pub(crate) const OPERATORS: [char; 5] = [
+pub(crate) const OPERATORS: [char; 9] = [
     '(',  // s-expression beg 
     ')',  // s-expression end
+    '[',  // vector literal beg
+    ']',  // vector literal end
+    '{',  // map literal beg
+    '}',  // map literal end
     '\'', // quote 
     '\"', // string
     ','   // quote escape
 ];
 
-pub(crate) const METAS: [char; 4] = [
-    '[',
-    ']',
-    '{',
-    '}'
-];
+/// Source location of a token or expression
+/// 
+/// ## Note
+/// - `line` and `col` are 1-based, counted in characters
+/// - `beg..end` is the byte range within the source
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    pub file: Shared<str>,
+    pub line: usize,
+    pub col: usize,
+    pub beg: usize,
+    pub end: usize
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.col)
+    }
+}
+
+impl Span {
+    /// Returns a `Span` starting at `self` and ending at `other`
+    pub fn to(&self, other: &Span) -> Span {
+        Span {
+            end: other.end,
+            ..self.clone()
+        }
+    }
+}
 
 /// Represents a lexical token
 /// 
 /// ## Example
-/// ```
+/// ```text
 /// (+ 1 2 3)
 /// ```
 /// 
 /// breaks into `Tok`s: 
-/// ```
+/// ```text
 /// ['(', '+', '1', '2', '3', ')']
 /// ```
 #[derive(Clone, PartialEq)]
 pub(crate) struct Tok {
     pub tok_type: TokType,
+    pub span: Span,
     pub id: usize
 }
 
@@ -52,29 +84,69 @@ impl Debug for Tok {
 /// Represents the type of a token
 /// 
 /// ## Example
-/// ```
+/// ```text
 /// (+ 1 2 3)
 /// ```
 /// 
 /// breaks into `TokType`s: 
-/// ```
+/// ```text
 /// [Beg, Sym, Sym, Sym, Sym, End]
 /// ```
 #[derive(Clone, PartialEq)]
 pub(crate) enum TokType {
     Sym(String),
-    Beg,
-    End,
+    Beg(Delim),
+    End(Delim),
     Esc,
     Qte,
 }
 
+/// The bracket opening or closing an expression
+/// 
+/// ## Note
+/// `Bracket` and `Brace` expressions are vector and map 
+/// literals, and `Quasi` a quoted list with escapes, read as
+/// ```text
+/// [a b c]       => (vector a b c)
+/// {k1 v1 k2 v2} => (hash-map k1 v1 k2 v2)
+/// '(a ,b)       => (list 'a b)
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum Delim {
+    #[default]
+    Paren,
+    Bracket,
+    Brace,
+    Quasi
+}
+
+impl Delim {
+    /// Returns the opening and closing brackets
+    pub(crate) fn brackets(&self) -> (char, char) {
+        match self {
+            Delim::Paren | Delim::Quasi => ('(', ')'),
+            Delim::Bracket => ('[', ']'),
+            Delim::Brace   => ('{', '}')
+        }
+    }
+
+    /// Returns the bridge a literal is read as a call to
+    pub(crate) fn head(&self) -> Option<&'static str> {
+        match self {
+            Delim::Paren   => None,
+            Delim::Bracket => Some("vector"),
+            Delim::Brace   => Some("hash-map"),
+            Delim::Quasi   => Some("list")
+        }
+    }
+}
+
 impl Debug for TokType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             TokType::Sym(_) => write!(f, "Sym"),
-            TokType::Beg    => write!(f, "Beg"),
-            TokType::End    => write!(f, "End"),
+            TokType::Beg(_) => write!(f, "Beg"),
+            TokType::End(_) => write!(f, "End"),
             TokType::Esc    => write!(f, "Esc"),
             TokType::Qte    => write!(f, "Qte")
         }
@@ -82,9 +154,10 @@ impl Debug for TokType {
 }
 
 impl TokType {
-    pub(crate) fn to_tok(self, id: usize) -> Tok {
+    pub(crate) fn into_tok(self, id: usize, span: Span) -> Tok {
         Tok { 
             tok_type: self, 
+            span,
             id 
         }
     }
@@ -97,6 +170,7 @@ pub(crate) struct Expr {
     pub end_id: isize,
     pub qte: bool,
     pub esc: bool,
+    pub delim: Delim,
     pub elems: Vec<Expr>
 }
 
@@ -142,4 +216,31 @@ impl Expr {
 
         false
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::env::Env;
+
+    /// Returns the position and text of the form `src` fails at
+    fn failing_form(src: &str) -> (String, &str) {
+        let env = Env::new().unwrap();
+        let err = env.eval_str(src).unwrap_err();
+        let span = err.span().expect("errors are located");
+
+        (span.to_string(), &src[span.beg..span.end])
+    }
+
+    #[test]
+    fn spans_point_at_the_failing_form() {
+        assert_eq!(failing_form("  (nosuch 1)"), ("<string>:1:3".to_string(), "(nosuch 1)"));
+        assert_eq!(failing_form("(+ 1 2)\n  (nosuch 5)"), ("<string>:2:3".to_string(), "(nosuch 5)"));
+        assert_eq!(failing_form("(+ 1\n  \"a\")"), ("<string>:1:1".to_string(), "(+ 1\n  \"a\")"));
+    }
+
+    #[test]
+    fn spans_point_into_function_bodies() {
+        let src = "(set x 1)\n(defun f (n)\n  (/ n 0))\n(f x)";
+        assert_eq!(failing_form(src), ("<string>:3:3".to_string(), "(/ n 0)"));
+    }
+}
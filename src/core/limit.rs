@@ -0,0 +1,361 @@
+use std::{
+    cell::Cell,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed}
+    },
+    time::{Duration, Instant}
+};
+
+use super::{
+    env::Env,
+    obj::Obj,
+    sync::Lock,
+    err::{Err, ErrType::*}
+};
+
+/// Steps run between reads of the clock
+const CLOCK_EVERY: usize = 256;
+
+thread_local! {
+    /// List elements and string bytes made on this thread
+    static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Counts `n` list elements or string bytes as allocated
+pub(crate) fn alloc(n: usize) {
+    ALLOCATED.with(|allocated| allocated.set(allocated.get().wrapping_add(n)));
+}
+
+fn allocated() -> usize {
+    ALLOCATED.with(Cell::get)
+}
+
+/// Caps on the work of each evaluation the host starts,
+/// where `None` is unlimited
+///
+/// ## Note
+/// - A limit is checked between steps, so a single bridge
+///   call may go past it before the evaluation fails
+/// - By default only `depth` is capped, at `DEFAULT_DEPTH`,
+///   so runaway recursion fails with an error instead of
+///   overflowing the stack of the thread
+///
+/// ## Example
+/// ```
+/// # use std::time::Duration;
+/// # use jester::{Env, Limits};
+/// # fn main() -> jester::Err {
+/// let env = Env::builder()
+///     .limits(Limits {
+///         steps: Some(100_000),
+///         depth: Some(500),
+///         time: Some(Duration::from_secs(1)),
+///         ..Limits::default()
+///     })
+///     .build()?;
+///
+/// let err = env.eval_str("(loop True 1)").unwrap_err();
+/// assert_eq!(err.kind(), "step-limit");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// forms evaluated and bytecode ops run
+    pub steps: Option<usize>,
+    /// nested evaluations, one per call that isn't in tail position
+    pub depth: Option<usize>,
+    /// list elements and string bytes made
+    pub allocs: Option<usize>,
+    /// wall-clock time
+    pub time: Option<Duration>
+}
+
+impl Limits {
+    /// Deepest evaluations may nest by default, which fits in the
+    /// 8MB stack of the main thread in debug builds, and in the
+    /// 2MB of a spawned thread in release builds. A host running
+    /// on a bigger stack may allow more
+    pub const DEFAULT_DEPTH: usize = 400;
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            steps: None,
+            depth: Some(Self::DEFAULT_DEPTH),
+            allocs: None,
+            time: None
+        }
+    }
+}
+
+/// Cancels the evaluations running on an `Env`, from any thread
+#[derive(Clone, Debug)]
+pub struct CancelHandle {
+    flag: Arc<AtomicBool>
+}
+
+impl CancelHandle {
+    /// Makes the running evaluations fail with
+    /// a `Cancelled` error at their next step
+    ///
+    /// ## Note
+    /// The request holds until `reset`, so evaluations
+    /// started after it fail at their first step too
+    pub fn cancel(&self) {
+        self.flag.store(true, Relaxed);
+    }
+
+    /// Drops a request to cancel, letting evaluations run again
+    pub fn reset(&self) {
+        self.flag.store(false, Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Relaxed)
+    }
+}
+
+/// Limits of the evaluations on an `Env`
+#[derive(Default)]
+pub(crate) struct Meter {
+    limits: Limits,
+    cancel: Arc<AtomicBool>
+}
+
+/// A clone keeps the limits, with its own cancel handle
+impl Clone for Meter {
+    fn clone(&self) -> Self {
+        Self {
+            limits: self.limits,
+            ..Self::default()
+        }
+    }
+}
+
+/// Work done by the evaluation a thread runs on an `Env`
+#[derive(Default)]
+pub(crate) struct Work {
+    depth: AtomicUsize,
+    steps: AtomicUsize,
+    /// `allocated()` when the evaluation started
+    base: AtomicUsize,
+    deadline: Lock<Option<Instant>>
+}
+
+impl Work {
+    /// Returns whether no evaluation is running
+    pub(crate) fn is_idle(&self) -> bool {
+        self.depth.load(Relaxed) == 0
+    }
+
+    /// Resets the counters for a new evaluation under `meter`
+    fn start(&self, meter: &Meter) {
+        self.steps.store(0, Relaxed);
+        self.base.store(allocated(), Relaxed);
+        *self.deadline.write() = meter.limits.time.map(|time| Instant::now() + time);
+    }
+}
+
+impl Env {
+    pub fn limits(&self) -> Limits {
+        self.meter.limits
+    }
+
+    /// Sets the limits of the evaluations started from now on
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.meter.limits = limits;
+    }
+
+    /// Returns a handle cancelling evaluations of `self`
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::time::Duration;
+    /// # let env = jester::Env::new().unwrap();
+    /// let cancel = env.cancel_handle();
+    /// let remote = cancel.clone();
+    ///
+    /// std::thread::spawn(move || {
+    ///     std::thread::sleep(Duration::from_millis(100));
+    ///     remote.cancel();
+    /// });
+    ///
+    /// let err = env.eval_str("(loop True 1)").unwrap_err();
+    /// assert_eq!(err.kind(), "cancelled");
+    ///
+    /// // lets the next evaluation run
+    /// cancel.reset();
+    /// ```
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle {
+            flag: Arc::clone(&self.meter.cancel)
+        }
+    }
+
+    /// Runs `body` one evaluation deeper, starting the
+    /// counters if no evaluation is running on this thread
+    pub(crate) fn metered<F>(&self, body: F) -> Err<Obj>
+    where
+        F: FnOnce() -> Err<Obj>
+    {
+        let context = self.context();
+        let work = &context.work;
+        let depth = work.depth.fetch_add(1, Relaxed) + 1;
+
+        if depth == 1 {
+            work.start(&self.meter);
+        }
+
+        let res = match self.meter.limits.depth {
+            Some(max) if depth > max => Err(DepthLimit(max)),
+            _ => body()
+        };
+
+        work.depth.fetch_sub(1, Relaxed);
+        self.release_context(&context);
+
+        res
+    }
+
+    /// Counts a step of the evaluation running on
+    /// this thread, erroring if it went past a limit
+    pub(crate) fn step(&self) -> Err {
+        let meter = &self.meter;
+        let limits = &meter.limits;
+
+        if meter.cancel.load(Relaxed) {
+            return Err(Cancelled)
+        }
+
+        let context = self.context();
+        let work = &context.work;
+        let steps = work.steps.fetch_add(1, Relaxed) + 1;
+
+        if let Some(max) = limits.steps {
+            if steps > max {
+                return Err(StepLimit(max))
+            }
+        }
+
+        if let Some(max) = limits.allocs {
+            if allocated().wrapping_sub(work.base.load(Relaxed)) > max {
+                return Err(AllocLimit(max))
+            }
+        }
+
+        if let Some(time) = limits.time {
+            let late = steps.is_multiple_of(CLOCK_EVERY) && work.deadline
+                .read()
+                .is_some_and(|deadline| Instant::now() > deadline);
+
+            if late {
+                return Err(TimeLimit(time))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runaway_recursion_fails_by_default() {
+        // the stack the default depth fits, in debug builds
+        let runner = std::thread::Builder::new().stack_size(8 << 20);
+
+        let kind = runner
+            .spawn(|| {
+                let env = Env::new().unwrap();
+                env.eval_str("(defun f (n) (+ 1 (f n)))").unwrap();
+                env.eval_str("(f 1)").unwrap_err().kind().to_string()
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert_eq!(kind, "depth-limit");
+    }
+
+    /// Returns the kind of error `src` fails with under `limits`,
+    /// in both evaluators
+    fn fails(limits: Limits, src: &str) -> [String; 2] {
+        [false, true].map(|interpreted| {
+            let env = Env::builder()
+                .interpreted(interpreted)
+                .limits(limits)
+                .build()
+                .unwrap();
+
+            env.eval_str("(defun spin () (loop True 1))").unwrap();
+            env.eval_str(src).unwrap_err().kind().to_string()
+        })
+    }
+
+    #[test]
+    fn each_limit_fails_with_its_kind() {
+        let limits = Limits::default();
+
+        let steps = Limits { steps: Some(10_000), ..limits };
+        assert_eq!(fails(steps, "(spin)"), ["step-limit", "step-limit"]);
+
+        let depth = Limits { depth: Some(50), ..limits };
+        assert_eq!(fails(depth, "(defun f (n) (+ 1 (f n))) (f 1)"), ["depth-limit", "depth-limit"]);
+
+        let allocs = Limits { allocs: Some(1_000), ..limits };
+        assert_eq!(fails(allocs, "(let (l ()) (loop True (append 1 l)))"), ["alloc-limit", "alloc-limit"]);
+
+        let time = Limits { time: Some(Duration::from_millis(50)), ..limits };
+        assert_eq!(fails(time, "(spin)"), ["time-limit", "time-limit"]);
+    }
+
+    #[test]
+    fn limits_hold_through_catch() {
+        let steps = Limits { steps: Some(10_000), ..Limits::default() };
+        assert_eq!(fails(steps, "(try (spin) (catch e 0))"), ["step-limit", "step-limit"]);
+    }
+
+    #[test]
+    fn limits_count_each_evaluation() {
+        let env = Env::builder()
+            .limits(Limits { steps: Some(1_000), ..Limits::default() })
+            .build()
+            .unwrap();
+
+        for _ in 0..10 {
+            assert!(env.eval_str("(let (i 0) (loop (< i 20) (incr i 1)) i)").is_ok());
+        }
+    }
+
+    #[test]
+    fn cancel_stops_running_evaluations() {
+        let env = Env::new().unwrap();
+        let cancel = env.cancel_handle();
+
+        let remote = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            cancel.cancel();
+        });
+
+        assert_eq!(env.eval_str("(loop True 1)").unwrap_err().kind(), "cancelled");
+        remote.join().unwrap();
+    }
+
+    #[test]
+    fn cancel_holds_until_reset() {
+        let env = Env::new().unwrap();
+        let cancel = env.cancel_handle();
+
+        cancel.cancel();
+        assert_eq!(env.eval_str("(+ 1 2)").unwrap_err().kind(), "cancelled");
+        assert_eq!(env.eval_str("(+ 1 2)").unwrap_err().kind(), "cancelled");
+
+        cancel.reset();
+        assert_eq!(env.eval_str("(+ 1 2)").unwrap(), Obj::from(3));
+    }
+}
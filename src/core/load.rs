@@ -0,0 +1,236 @@
+use std::{
+    io,
+    path::{Path, PathBuf}
+};
+
+use super::{
+    env::Env,
+    obj::Obj,
+    err::{Err, ErrType::*}
+};
+
+/// Environment variable listing extra script directories
+pub const PATH_VAR: &str = "JESTER_PATH";
+
+/// Extension of `Jester-rs` scripts
+pub const SCRIPT_EXT: &str = "lsp";
+
+impl Env {
+    /// Adds `dir` to the directories searched by `load` and `require`
+    pub fn add_path<P: AsRef<Path>>(&self, dir: P) {
+        self.paths
+            .write()
+            .push(dir.as_ref().to_path_buf());
+    }
+
+    /// Returns the directories scripts are searched in, in order
+    ///
+    /// ## Note
+    /// - the directory of the script being loaded, or
+    ///   the working directory outside of scripts
+    /// - the directories in `JESTER_PATH`
+    /// - the directories added by `Env::add_path`
+    pub fn search_paths(&self) -> Vec<PathBuf> {
+        let script_dir = self.context()
+            .loading
+            .read()
+            .last()
+            .and_then(|file| file.parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let mut dirs = vec![script_dir];
+
+        if let Some(var) = std::env::var_os(PATH_VAR) {
+            dirs.extend(std::env::split_paths(&var));
+        }
+
+        dirs.extend(self.paths.read().iter().cloned());
+        dirs
+    }
+
+    /// Finds script `path` in the search paths, also
+    /// trying it with a `.lsp` extension if it has none
+    pub fn find_script<P: AsRef<Path>>(&self, path: P) -> Option<PathBuf> {
+        let path = path.as_ref();
+        let mut names = vec![path.to_path_buf()];
+
+        if path.extension().is_none() {
+            names.push(path.with_extension(SCRIPT_EXT));
+        }
+
+        if path.is_absolute() {
+            return names
+                .into_iter()
+                .find(|name| name.is_file())
+        }
+
+        self.search_paths()
+            .iter()
+            .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+            .find(|file| file.is_file())
+    }
+
+    /// Evaluates the script at `path`, found in the search paths
+    pub fn load_file<P: AsRef<Path>>(&self, path: P) -> Err<Obj> {
+        let path = path.as_ref();
+
+        match self.find_script(path) {
+            Some(file) => self.eval_file(&file),
+            None => Err(IoErr(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("couldn't find script '{}'", path.display())
+            )))
+        }
+    }
+
+    /// Evaluates script `name` unless it was already required
+    ///
+    /// ## Note
+    /// `name` is searched as `name.lsp`, with `/` separating
+    /// directories, so `(require 'utils)` loads `utils.lsp`
+    /// and `(require "text/format")` loads `text/format.lsp`
+    pub fn require(&self, name: &str) -> Err<Obj> {
+        let file = self
+            .find_script(Path::new(name).with_extension(SCRIPT_EXT))
+            .ok_or_else(|| NonMod(name.to_string()))?;
+
+        let file = file.canonicalize()?;
+
+        if self.loaded.read().contains(&file) {
+            return Ok(Obj::Bool(false))
+        }
+
+        self.eval_file(&file)?;
+        self.loaded
+            .write()
+            .insert(file);
+
+        Ok(Obj::Bool(true))
+    }
+
+    /// Evaluates the script at `path`, returning the value of
+    /// its last form, erroring if it is already being loaded
+    ///
+    /// ## Note
+    /// A first line starting with `#!` is skipped, so a
+    /// script can be run as `./script.lsp` on Unix
+    pub fn eval_file<P: AsRef<Path>>(&self, path: P) -> Err<Obj> {
+        let file = path.as_ref();
+        let canon = file.canonicalize()?;
+
+        let context = self.context();
+
+        if let Some(pos) = context.loading.read().iter().position(|rhs| rhs == &canon) {
+            let cycle = context.loading.read()[pos..]
+                .iter()
+                .chain(std::iter::once(&canon))
+                .map(|file| file.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+
+            return Err(CycMod(cycle))
+        }
+
+        let src = std::fs::read_to_string(&canon)?;
+
+        context.loading.write().push(canon);
+        let res = self.eval_source(skip_shebang(&src), &file.display().to_string());
+        context.loading.write().pop();
+
+        res
+    }
+}
+
+/// Returns `src` without its `#!` line, keeping
+/// the newline so the lines of errors still match
+fn skip_shebang(src: &str) -> &str {
+    match src.strip_prefix("#!") {
+        Some(rest) => rest
+            .find('\n')
+            .map_or("", |end| &rest[end..]),
+        None => src
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use crate::core::{builder::EnvBuilder, env::Env};
+
+    /// Writes each of `scripts`, as a path and its source,
+    /// to a new directory named after `test`
+    fn scripts(test: &str, scripts: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("jester-{}-{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        for (path, src) in scripts {
+            let file = dir.join(path);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, src).unwrap();
+        }
+
+        dir
+    }
+
+    fn eval(env: &Env, src: &str) -> String {
+        match env.eval_str(src) {
+            Ok(obj) => obj.display(env),
+            Err(err) => format!("error: {}", err.kind())
+        }
+    }
+
+    #[test]
+    fn require_loads_once() {
+        let dir = scripts("require", &[("count.lsp", "(set loads (+ loads 1))")]);
+        let env = EnvBuilder::new().path(&dir).build().unwrap();
+
+        eval(&env, "(set loads 0)");
+        assert_eq!(eval(&env, "(list (require 'count) (require \"count\") loads)"), "(true false 1)");
+        assert_eq!(eval(&env, "(load \"count\") (load \"count.lsp\") loads"), "3");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn scripts_find_their_neighbours() {
+        let dir = scripts("neighbours", &[
+            ("lib/main.lsp", "(require 'text/util) (util 1)"),
+            ("lib/text/util.lsp", "(defun util (x) (+ x 1))")
+        ]);
+        let env = Env::new().unwrap();
+
+        let main = dir.join("lib/main.lsp");
+        assert_eq!(env.eval_file(&main).unwrap().display(&env), "2");
+        assert_eq!(eval(&env, "(require 'text/util)"), "error: undefined-module");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn cyclic_requires_fail() {
+        let dir = scripts("cyclic", &[
+            ("a.lsp", "(require 'b)"),
+            ("b.lsp", "(require 'a)")
+        ]);
+        let env = EnvBuilder::new().path(&dir).build().unwrap();
+
+        assert_eq!(eval(&env, "(require 'a)"), "error: cyclic-require");
+        assert_eq!(eval(&env, "(load \"missing\")"), "error: io");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn shebang_lines_keep_error_lines() {
+        let dir = scripts("shebang", &[("run.lsp", "#!/usr/bin/env jester\n(/ 1 0)")]);
+        let env = Env::new().unwrap();
+
+        let err = env.eval_file(dir.join("run.lsp")).unwrap_err();
+        assert_eq!(err.kind(), "div-by-zero");
+        assert_eq!(err.span().unwrap().line, 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
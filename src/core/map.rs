@@ -0,0 +1,220 @@
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher}
+};
+
+use super::{
+    obj::Obj::{self, *},
+    err::Err,
+    node::Node,
+    rc_cell::RcCell,
+    sync::Shared,
+    gc::{Trace, Tracer}
+};
+
+/// Type name reported for objects that can't be map keys
+const HASHABLE: &str = "hashable";
+
+/// An `Obj` usable as a `Map` key
+///
+/// ## Note
+/// Keys hash and compare by value, with numbers
+/// equal across types as in `Obj::eq`
+/// ```text
+/// numbers, strings, booleans, nil, symbols, lists and vectors of keys
+/// ```
+#[derive(Clone)]
+pub struct Key(Obj);
+
+impl Key {
+    /// Wraps `obj`, erroring if it can't be hashed
+    pub fn new(obj: Obj) -> Err<Self> {
+        Self::hashable(&obj)?;
+        Ok(Self(obj))
+    }
+
+    fn hashable(obj: &Obj) -> Err {
+        match obj {
+            I32(_) | I64(_) | I128(_) | F64(_) | Bool(_) | Str(_) | Sym(_) | Nil(_) => Ok(()),
+            Lst(node) | Vct(node) => node
+                .iter()
+                .try_for_each(|elem| Self::hashable(&elem.as_ref())),
+            _ => Err(obj.mis_type(HASHABLE))
+        }
+    }
+
+    pub fn as_obj(&self) -> &Obj {
+        &self.0
+    }
+
+    pub fn into_obj(self) -> Obj {
+        self.0
+    }
+
+    /// Returns the bits numbers hash by,
+    /// treating `-0.0` and `0.0` the same
+    fn num_bits(num: f64) -> u64 {
+        if num == 0.0 { 0 } else { num.to_bits() }
+    }
+
+    fn hash_obj<H: Hasher>(obj: &Obj, state: &mut H) {
+        // tag each kind, so a string can't collide with a symbol
+        match obj {
+            Str(x)  => (0, x).hash(state),
+            Bool(x) => (1, x).hash(state),
+            Sym(x)  => (2, Shared::as_ptr(x.as_raw())).hash(state),
+            Nil(_)  => 3.hash(state),
+            Lst(x) | Vct(x) => {
+                (4, x.len()).hash(state);
+                x.iter().for_each(|elem| Self::hash_obj(&elem.as_ref(), state));
+            }
+            num     => {
+                if let Ok(num) = num.is_num() {
+                    (5, Self::num_bits(num)).hash(state)
+                }
+            }
+        }
+    }
+
+    fn eq_obj(lhs: &Obj, rhs: &Obj) -> bool {
+        match (lhs, rhs) {
+            (Str(x), Str(y))   => x == y,
+            (Bool(x), Bool(y)) => x == y,
+            (Sym(x), Sym(y))   => x.raw_eq(y),
+            (Nil(_), Nil(_))   => true,
+            (Lst(x), Lst(y)) | (Vct(x), Vct(y)) => {
+                x.len() == y.len() && x
+                    .iter()
+                    .zip(y.iter())
+                    .all(|(x, y)| Self::eq_obj(&x.as_ref(), &y.as_ref()))
+            }
+            _ => match (lhs.is_num(), rhs.is_num()) {
+                (Ok(x), Ok(y)) => x == y,
+                _ => false
+            }
+        }
+    }
+}
+
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Self::hash_obj(&self.0, state)
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Self) -> bool {
+        Self::eq_obj(&self.0, &other.0)
+    }
+}
+
+impl Eq for Key {}
+
+/// `Jester-rs` hash map, iterated in insertion order
+#[derive(Clone, Default)]
+pub struct Map {
+    entries: Vec<(Key, Obj)>,
+    index: HashMap<Key, usize>
+}
+
+/// ## Note
+/// Keys are held twice, by the entries and the index
+impl Trace for Map {
+    fn trace(&self, tracer: &mut Tracer) {
+        for (key, val) in &self.entries {
+            key.0.trace(tracer);
+            val.trace(tracer);
+        }
+
+        self.index
+            .keys()
+            .for_each(|key| key.0.trace(tracer));
+    }
+}
+
+impl Map {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, key: &Key) -> Option<&Obj> {
+        self.index
+            .get(key)
+            .map(|i| &self.entries[*i].1)
+    }
+
+    pub fn contains(&self, key: &Key) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Inserts `val` at `key`, returning the previous value
+    pub fn insert(&mut self, key: Key, val: Obj) -> Option<Obj> {
+        match self.index.get(&key) {
+            Some(i) => Some(std::mem::replace(&mut self.entries[*i].1, val)),
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, val));
+                None
+            }
+        }
+    }
+
+    /// Removes `key`, returning its value
+    pub fn remove(&mut self, key: &Key) -> Option<Obj> {
+        let i = self.index.remove(key)?;
+        let (_, val) = self.entries.remove(i);
+
+        // shift the indices of the entries after `i`
+        for idx in self.index.values_mut() {
+            if *idx > i {
+                *idx -= 1;
+            }
+        }
+
+        Some(val)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Key, &Obj)> {
+        self.entries
+            .iter()
+            .map(|(key, val)| (key, val))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Key> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn vals(&self) -> impl Iterator<Item = &Obj> {
+        self.iter().map(|(_, val)| val)
+    }
+
+    /// Returns the `i`th entry as a `(key value)` list
+    pub fn entry(&self, i: usize) -> Option<Node> {
+        self.entries
+            .get(i)
+            .map(|(key, val)| Node::from(vec![
+                RcCell::from(key.as_obj().clone()),
+                RcCell::from(val.clone())
+            ]))
+    }
+
+    /// Returns whether both maps hold equal entries
+    pub fn eq(&self, other: &Map) -> Err<bool> {
+        if self.len() != other.len() {
+            return Ok(false)
+        }
+
+        for (key, val) in self.iter() {
+            match other.get(key) {
+                Some(rhs) if val.eq(rhs)? => (),
+                _ => return Ok(false)
+            }
+        }
+
+        Ok(true)
+    }
+}
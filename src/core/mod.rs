@@ -1,4 +1,5 @@
 pub mod id;
+pub mod builder;
 pub mod env;
 pub mod err;
 pub mod eval;
@@ -8,5 +9,14 @@ pub mod parse;
 pub mod obj;
 pub mod op;
 pub mod rc_cell;
+pub mod sync;
+pub mod scope;
 pub mod type_id;
-pub mod lex;
\ No newline at end of file
+pub mod convert;
+pub mod gc;
+pub mod limit;
+pub mod vm;
+pub mod lex;
+pub mod load;
+pub mod map;
+pub mod module;
\ No newline at end of file
@@ -0,0 +1,422 @@
+use super::{
+    env::Env,
+    obj::Obj,
+    node::{Node, NodeIter},
+    rc_cell::RcCell,
+    sync::Shared,
+    err::{Err, ErrType::*}
+};
+
+/// Separates a module from an item in a qualified symbol
+pub const PATH_SEP: &str = "::";
+
+/// Forms that define a module item, named by their first argument
+const DEFINERS: [&str; 5] = ["defun", "defun*", "defmacro", "defmacro*", "set"];
+
+/// Name of the form listing the items a module exports
+pub(crate) const EXPORT: &str = "export";
+
+/// A named group of definitions, defined by `module`
+///
+/// ## Note
+/// Each item of module `m` lives in the symbol `m::item`.
+/// Only exported items can be named from outside the module
+/// ```text
+/// (module math
+///     (export square)
+///     (defun helper (x) (* x x))
+///     (defun square (x) (helper x)))
+///
+/// (math::square 4)  ; returns 16
+/// (math::helper 4)  ; undefined symbol
+/// ```
+#[derive(Debug)]
+pub struct Module {
+    name: String,
+    exports: Vec<String>
+}
+
+impl Module {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn exports(&self) -> &[String] {
+        &self.exports
+    }
+
+    pub fn is_exported(&self, item: &str) -> bool {
+        self.exports
+            .iter()
+            .any(|rhs| rhs == item)
+    }
+
+    /// Returns the qualified name of `item`
+    pub fn path(&self, item: &str) -> String {
+        format!("{}{}{}", self.name, PATH_SEP, item)
+    }
+}
+
+impl Env {
+    /// Returns the module named or aliased `name`
+    pub fn get_module(&self, name: &str) -> Err<Shared<Module>> {
+        self.modules
+            .read()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| NonMod(name.to_string()))
+    }
+
+    /// Returns the names and aliases of the modules defined, sorted
+    pub fn module_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.modules
+            .read()
+            .keys()
+            .cloned()
+            .collect();
+
+        names.sort();
+        names
+    }
+
+    /// Removes module or alias `name`, so it can be defined again
+    /// 
+    /// ## Note
+    /// Its items keep their `name::item` symbols, so code that 
+    /// refers to them sees the items of the module defined next
+    pub fn remove_module(&self, name: &str) -> Option<Shared<Module>> {
+        self.modules
+            .write()
+            .remove(name)
+    }
+
+    /// Returns the unqualified name of symbol `obj`
+    /// 
+    /// ## Note
+    /// An imported symbol is read as the item it imports, so
+    /// `square` imported from `math` is named `square` too
+    pub fn get_item_name(&self, obj: &RcCell<Obj>) -> Option<String> {
+        self.get_sym_name(obj).map(|name| match name.rsplit_once(PATH_SEP) {
+            Some((_, item)) if !item.is_empty() => item.to_string(),
+            _ => name
+        })
+    }
+
+    /// Returns the cell the reader binds symbol `name` to: an
+    /// imported item, an exported module item if `name` is
+    /// qualified, or else the global symbol `name`
+    pub(crate) fn intern(&self, name: &str) -> Err<RcCell<Obj>> {
+        if let Some(cell) = self.imports.read().get(name) {
+            return Ok(cell.clone())
+        }
+
+        match name.rsplit_once(PATH_SEP) {
+            Some((module, item)) if !module.is_empty() && !item.is_empty() => {
+                let module = self.get_module(module)?;
+
+                if !module.is_exported(item) {
+                    return Err(NonSym(name.to_string()))
+                }
+
+                Ok(self.intern_sym(&module.path(item)))
+            }
+            _ => Ok(self.intern_sym(name))
+        }
+    }
+
+    /// Defines module `name` from `body`, moving each item it
+    /// defines into its own `name::item` symbol before evaluating
+    ///
+    /// ## Note
+    /// Calls in `body` to macros defined outside the module are
+    /// expanded first, so items they define are moved too, while
+    /// quoted data keeps the symbols it was read with
+    pub fn define_module(&self, name: &str, body: NodeIter) -> Err<Obj> {
+        if self.modules.read().contains_key(name) {
+            return Err(DupMod(name.to_string()))
+        }
+
+        let mut module = Module {
+            name: name.to_string(),
+            exports: Vec::new()
+        };
+
+        let export = self.get_sym(EXPORT).unwrap();
+        let quote = self.intern_sym("quote");
+        let definers: Vec<_> = DEFINERS
+            .iter()
+            .filter_map(|def| self.get_sym(def))
+            .collect();
+
+        let mut items = Vec::new();
+        let mut renames: Vec<(RcCell<Obj>, RcCell<Obj>)> = Vec::new();
+
+        for form in body {
+            self.expand_outer(form, &renames)?;
+            let form = form.as_ref();
+
+            let node = match &*form {
+                Obj::Lst(node) => node,
+                _ => continue
+            };
+
+            let head = match node.get(0) {
+                Ok(head) => head,
+                _ => continue
+            };
+
+            match &*head {
+                Obj::Sym(head) if head.raw_eq(&export) => {
+                    for item in node.iter_from(1) {
+                        let item = item.as_ref();
+                        module.exports.push(self.get_item_name(item.is_symbol()?).unwrap());
+                    }
+                }
+
+                Obj::Sym(head) if definers.iter().any(|def| head.raw_eq(def)) => {
+                    let item = match node.get(1).as_deref() {
+                        Ok(Obj::Sym(item)) => item.clone(),
+                        _ => continue
+                    };
+
+                    let item = &item;
+
+                    if renames.iter().any(|(from, _)| from.raw_eq(item)) {
+                        continue
+                    }
+
+                    let name = self.get_item_name(item).unwrap();
+                    renames.push((item.clone(), self.intern_sym(&module.path(&name))));
+                    items.push(name);
+                }
+
+                _ => ()
+            }
+        }
+
+        if let Some(item) = module.exports.iter().find(|item| !items.contains(item)) {
+            return Err(NonSym(module.path(item)))
+        }
+
+        for form in body {
+            rename(form, &renames, &quote)?;
+        }
+
+        for form in body {
+            let form = form.clone_inner();
+
+            match &form {
+                Obj::Lst(node) if matches!(node.get(0).as_deref(), Ok(Obj::Sym(head)) if head.raw_eq(&export)) => (),
+                obj => { self.eval(obj)?; }
+            }
+        }
+
+        self.modules
+            .write()
+            .insert(name.to_string(), Shared::new(module));
+
+        Ok(Obj::Nil(()))
+    }
+
+    /// Replaces `form` by its expansion while it calls a macro
+    /// not among `renames`, the items the module defined so far
+    fn expand_outer(&self, form: &RcCell<Obj>, renames: &[(RcCell<Obj>, RcCell<Obj>)]) -> Err {
+        loop {
+            let exp = match &*form.as_ref() {
+                Obj::Lst(node) => match node.get(0).as_deref() {
+                    Ok(Obj::Sym(head)) if !renames.iter().any(|(from, _)| from.raw_eq(head)) => {
+                        match head.clone_inner() {
+                            Obj::Macro(f) => detach(&f.expand(self, node.iter_from(1))?)?,
+                            _ => return Ok(())
+                        }
+                    }
+                    _ => return Ok(())
+                },
+                _ => return Ok(())
+            };
+
+            form.replace(exp)?;
+        }
+    }
+
+    /// Imports module `name`: under `alias` if given, or else
+    /// its exports, or only `items` if given, as unqualified
+    /// symbols for the forms read after
+    ///
+    /// ## Note
+    /// Each of `items` is an export and the name to import it as
+    pub fn import_module(&self, name: &str, alias: Option<&str>, items: Option<Vec<(String, String)>>) -> Err {
+        let module = self.get_module(name)?;
+
+        if let Some(alias) = alias {
+            let mut modules = self.modules.write();
+
+            if modules.contains_key(alias) {
+                return Err(DupMod(alias.to_string()))
+            }
+
+            modules.insert(alias.to_string(), module);
+            return Ok(())
+        }
+
+        let items = items.unwrap_or_else(|| module
+            .exports()
+            .iter()
+            .map(|item| (item.clone(), item.clone()))
+            .collect());
+
+        let mut cells = Vec::new();
+
+        for (item, as_name) in items {
+            if !module.is_exported(&item) {
+                return Err(NonSym(module.path(&item)))
+            }
+
+            let cell = self.intern_sym(&module.path(&item));
+
+            // the same name from another module is ambiguous
+            match self.imports.read().get(&as_name) {
+                Some(prev) if !prev.raw_eq(&cell) => return Err(DupSym(as_name)),
+                _ => cells.push((as_name, cell))
+            }
+        }
+
+        self.imports
+            .write()
+            .extend(cells);
+
+        Ok(())
+    }
+}
+
+/// Returns a copy of `obj` whose lists have cells of their own, so
+/// renaming an expansion leaves the body of its macro alone
+fn detach(obj: &Obj) -> Err<Obj> {
+    let copy = |node: &Node| -> Err<Node> {
+        let mut copy = node
+            .iter()
+            .mapped(|elem| detach(&elem.as_ref()))?;
+
+        if let Some(span) = node.span() {
+            copy.set_span(span.clone());
+        }
+
+        Ok(copy)
+    };
+
+    Ok(match obj {
+        Obj::Lst(node) => Obj::Lst(copy(node)?),
+        Obj::Vct(node) => Obj::Vct(copy(node)?),
+        obj => obj.clone()
+    })
+}
+
+/// Replaces each symbol in `form` found in `renames`, within
+/// nested lists other than those quoted by `quote`
+fn rename(form: &RcCell<Obj>, renames: &[(RcCell<Obj>, RcCell<Obj>)], quote: &RcCell<Obj>) -> Err {
+    let to = match &*form.as_ref() {
+        Obj::Sym(sym) => renames
+            .iter()
+            .find(|(from, _)| from.raw_eq(sym))
+            .map(|(_, to)| to.clone()),
+        Obj::Lst(node) if matches!(node.get(0).as_deref(), Ok(Obj::Sym(head)) if head.raw_eq(quote)) => None,
+        Obj::Lst(node) | Obj::Vct(node) => {
+            node.iter().try_for_each(|elem| rename(elem, renames, quote))?;
+            None
+        }
+        _ => None
+    };
+
+    if let Some(to) = to {
+        form.replace(Obj::Sym(to))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::env::Env;
+
+    fn eval(env: &Env, src: &str) -> String {
+        match env.eval_str(src) {
+            Ok(obj) => obj.display(env),
+            Err(err) => format!("error: {}", err.kind())
+        }
+    }
+
+    #[test]
+    fn quoted_data_keeps_its_symbols() {
+        let env = Env::new().unwrap();
+        eval(&env, "
+            (module m
+                (export f names)
+                (defun helper () 1)
+                (defun f () (list 'helper (helper)))
+                (set names '(helper f)))");
+
+        assert_eq!(eval(&env, "(m::f)"), "(HELPER 1)");
+        assert_eq!(eval(&env, "m::names"), "(HELPER F)");
+    }
+
+    #[test]
+    fn items_defined_by_macros_are_moved() {
+        let env = Env::new().unwrap();
+        eval(&env, "
+            (defmacro defconst (name v) '(set ,name ,v))
+            (defmacro defk () '(set k 1))
+            (module m
+                (export pi twice k)
+                (defconst pi 3)
+                (defk)
+                (defun twice () (* 2 pi)))");
+
+        assert_eq!(eval(&env, "(list m::pi (m::twice) m::k)"), "(3 6 1)");
+        assert_eq!(eval(&env, "(list pi k)"), "(nil nil)");
+
+        // the expansion renamed was a copy of the macro's body
+        assert_eq!(eval(&env, "(defk) k"), "1");
+    }
+
+    /// Defines `geo`, exporting `dist` but not its helper `square`
+    fn geo() -> Env {
+        let env = Env::new().unwrap();
+        eval(&env, "
+            (module geo
+                (export dist)
+                (defun square (x) (* x x))
+                (defun dist (x y) (+ (square x) (square y))))");
+
+        env
+    }
+
+    #[test]
+    fn only_exports_are_reachable() {
+        let env = geo();
+
+        assert_eq!(eval(&env, "(geo::dist 3 4)"), "25");
+        assert_eq!(eval(&env, "geo::square"), "error: undefined-symbol");
+        assert_eq!(eval(&env, "square"), "nil");
+        assert_eq!(eval(&env, "(module bad (export nope) (set a 1))"), "error: undefined-symbol");
+    }
+
+    #[test]
+    fn imports_name_exports() {
+        let env = geo();
+
+        assert_eq!(eval(&env, "(import geo ((dist as length))) (length 1 2)"), "5");
+        assert_eq!(eval(&env, "(import geo as g) (g::dist 1 1)"), "2");
+        assert_eq!(eval(&env, "(import geo) (dist 2 2)"), "8");
+    }
+
+    #[test]
+    fn module_names_clash() {
+        let env = geo();
+
+        assert_eq!(eval(&env, "(module geo (export a) (set a 1))"), "error: duplicate-module");
+        assert_eq!(eval(&env, "(import nomod)"), "error: undefined-module");
+
+        eval(&env, "(module geo2 (export dist) (defun dist () 0)) (import geo)");
+        assert_eq!(eval(&env, "(import geo2)"), "error: duplicate-symbol");
+    }
+}
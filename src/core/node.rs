@@ -1,29 +1,35 @@
-use std::ops::DerefMut;
 use std::ops::Index;
 
 use super::env::Env;
+use super::lex::Span;
 use super::obj::Obj;
 use super::err::Err;
-use super::err::ErrType::*;
+use super::err::ErrType::{self, *};
 use super::rc_cell::RcCell;
+use super::sync::{ReadGuard, WriteGuard};
+use super::scope::Scope;
+use super::fun::Tail;
+use super::gc::{Trace, Tracer};
     
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Node {
     buf: Vec<RcCell<Obj>>,
+    span: Option<Span>
 }
 
-impl Default for Node {
-    fn default() -> Self {
-        Node { 
-            buf: Vec::new() 
-        }
+impl Trace for Node {
+    fn trace(&self, tracer: &mut Tracer) {
+        self.buf
+            .iter()
+            .for_each(|item| tracer.cell(item));
     }
 }
 
 impl From<Vec<RcCell<Obj>>> for Node {
     fn from(items: Vec<RcCell<Obj>>) -> Self {
         Self {
-            buf: items 
+            buf: items,
+            span: None
         }
     }
 }
@@ -46,13 +52,23 @@ impl FromIterator<Obj> for Node {
     {
         Node::from(iter
             .into_iter()
-            .map(|obj| RcCell::from(obj))
+            .map(RcCell::from)
             .collect::<Vec<_>>())
     }
 }
 
 impl Node {
 
+    /// Returns the source location of `self`, if 
+    /// it was read from source code
+    pub fn span(&self) -> Option<&Span> {
+        self.span.as_ref()
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = Some(span)
+    }
+
     pub fn len(&self) -> usize {
         self.buf.len()
     }
@@ -67,7 +83,7 @@ impl Node {
 
     pub fn insert(&mut self, i: usize, item: RcCell<Obj>) -> Err {
         if i >= self.len() {
-            return Err(OutOfBound)
+            return Err(self.out_of_bound(i))
         }
         
         self.buf.insert(i, item);
@@ -76,35 +92,40 @@ impl Node {
 
     pub fn remove(&mut self, i: usize) -> Err<Obj> {
         if i >= self.len() {
-            return Err(OutOfBound)
+            return Err(self.out_of_bound(i))
         }
         
-        Ok(self.buf.remove(i).as_ref().clone())
+        Ok(self.buf.remove(i).clone_inner())
     }
 
     pub fn get_cell(&self, i: usize) -> Err<&RcCell<Obj>> {
         self.buf
             .get(i)
-            .ok_or(OutOfBound)
+            .ok_or_else(|| self.out_of_bound(i))
     }
 
-    pub fn get_mut(&self, i: usize) -> Err<&mut Obj> {
-        self.buf
-            .get(i)
-            .map(|obj| obj.as_mut())
-            .ok_or(OutOfBound)
+    /// Returns item `i` to write in place
+    /// 
+    /// ## Note
+    /// Fails with a `Borrowed` error if the item is being read
+    pub fn get_mut(&self, i: usize) -> Err<WriteGuard<'_, Obj>> {
+        self.get_cell(i)?.as_mut()
     }
 
-    pub fn get(&self, i: usize) -> Err<&Obj> {
-        self.buf
-            .get(i)
-            .map(|obj| obj.as_ref())
-            .ok_or(OutOfBound)
+    pub fn get(&self, i: usize) -> Err<ReadGuard<'_, Obj>> {
+        self.get_cell(i).map(|obj| obj.as_ref())
+    }
+
+    fn out_of_bound(&self, index: usize) -> ErrType {
+        OutOfBound { 
+            index, 
+            len: self.len() 
+        }
     }
 
     /// Returns evaluated clone
     pub fn evaled(&self, env: &Env) -> Err<Node> {
-        self.iter().mapped(|obj| env.eval(obj.as_ref()))
+        self.iter().mapped(|obj| env.eval(&obj.as_ref()))
     }
 
     /// Creates an iterator
@@ -163,11 +184,11 @@ impl<'a> NodeIter<'a> {
         self.node.get_cell(self.beg + i)
     }
 
-    pub fn get_mut(&self, i: usize) -> Err<&mut Obj> {
-        self.node.get_mut(self.beg + 1)
+    pub fn get_mut(&self, i: usize) -> Err<WriteGuard<'a, Obj>> {
+        self.node.get_mut(self.beg + i)
     }
 
-    pub fn get(&self, i: usize) -> Err<&Obj> {
+    pub fn get(&self, i: usize) -> Err<ReadGuard<'a, Obj>> {
         self.node.get(self.beg + i)
     }
 
@@ -176,12 +197,12 @@ impl<'a> NodeIter<'a> {
     }
 
     /// Return a new `Node` with elements mapped by `map`
-    pub fn mapped<F>(&self, mut map: F) -> Err<Node> 
+    pub fn mapped<F>(&self, map: F) -> Err<Node> 
     where 
         F: FnMut(&'a RcCell<Obj>) -> Err<Obj> 
     {       
         self
-            .map(|obj| map(obj))
+            .map(map)
             .collect::<_>()
     }
 
@@ -189,7 +210,7 @@ impl<'a> NodeIter<'a> {
     /// the evaluation of the last element
     /// 
     /// ## Example
-    /// ```
+    /// ```text
     /// // equivalent to "do"
     /// (set x (do 1, 2, 3, 4, 5))
     /// (assert-eq x 5)
@@ -200,8 +221,7 @@ impl<'a> NodeIter<'a> {
     {        
         let bounds = self
             .len()
-            .checked_sub(1)
-            .unwrap_or(0);  
+            .saturating_sub(1);
 
         for i in 0..bounds {
             map(self.get_cell(i)?)?;
@@ -220,8 +240,7 @@ impl<'a> NodeIter<'a> {
     {        
         let bounds = self
             .len()
-            .checked_sub(1)
-            .unwrap_or(0);  
+            .saturating_sub(1);
 
         for i in 0..bounds {
             map(self.get_cell(i)?, false)?;
@@ -230,11 +249,11 @@ impl<'a> NodeIter<'a> {
         map(self.get_cell(bounds)?, true)
     }
 
-    /// Evaluates each element, returning the
-    /// evaluation of the last element within a scope
+    /// Evaluates each element within `scope`, returning 
+    /// the evaluation of the last element
     /// 
     /// ## Example
-    /// ```
+    /// ```text
     /// // equivalent to "let"
     /// (set a nil)
     /// (set b 101)
@@ -247,61 +266,21 @@ impl<'a> NodeIter<'a> {
     /// (assert-eq b 101)
     /// (assert-eq x 30)
     /// ```
-    pub fn progn_scoped<'b, 'c, I1, I2>(&self, env: &Env, params: I1, args: I2) -> Err<Obj>     
-    where 
-        I1: Iterator<Item = &'b RcCell<Obj>> + Clone, 
-        I2: Iterator<Item = &'c RcCell<Obj>> + Clone,
-    {   
-        let prev: Node = params
-            .clone()
-            .map(|obj| env.eval(obj.as_ref()))
-            .collect::<Err<_>>()?;   
-        
-        for (param, arg) in params.clone().zip(args.clone()) { 
-            param
-                .as_mut()
-                .deref_mut()
-                .assign(&env.eval(arg.as_ref())?);
-        }
-
-        let res = self.progn(|obj| env.eval(obj.as_ref()));
-
-        for (param, val) in params.zip(prev.iter()) {   
-            param
-                .as_mut()
-                .deref_mut()
-                .assign(&env.eval(val.as_ref())?);
-        }
-
-        res
+    pub fn progn_scoped(&self, env: &Env, scope: Scope) -> Err<Obj> {
+        env.in_scope(RcCell::from(scope), || self.progn(|obj| env.eval(&obj.as_ref())))
     }
 
-    pub fn progn_macro<'b, 'c, I1, I2>(&self, env: &Env, params: I1, args: I2) -> Err<Obj>     
-    where 
-        I1: Iterator<Item = &'b RcCell<Obj>> + Clone, 
-        I2: Iterator<Item = &'c RcCell<Obj>> + Clone,
-    {   
-        let prev: Node = params
-            .clone()
-            .map(|obj| env.eval(obj.as_ref()))
-            .collect::<Err<_>>()?;   
-        
-        for (param, arg) in params.clone().zip(args.clone()) { 
-            param
-                .as_mut()
-                .deref_mut()
-                .assign(&arg.as_ref());
-        }
-
-        let res = self.progn(|obj| env.eval(obj.as_ref()));
+    /// Evaluates each element but the last, returning
+    /// the last element as a form in tail position
+    pub fn progn_tail(&self, env: &Env) -> Err<Tail> {
+        let bounds = self
+            .len()
+            .saturating_sub(1);
 
-        for (param, val) in params.zip(prev.iter()) {   
-            param
-                .as_mut()
-                .deref_mut()
-                .assign(&env.eval(val.as_ref())?);
+        for i in 0..bounds {
+            env.eval(&*self.get(i)?)?;
         }
 
-        res
+        Ok(Tail::Eval(self.get_cell(bounds)?.clone_inner()))
     }
 }
\ No newline at end of file
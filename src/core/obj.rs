@@ -1,10 +1,15 @@
+use std::fmt::{self, Debug, Display, Formatter};
+
 use super::{
     rc_cell::RcCell, 
+    sync::{ReadGuard, WriteGuard},
     type_id::TypeId,
     node::Node, 
+    map::Map,
     env::Env,
-    err::{Err, ErrType::*},
-    fun::{FnNative, Bridge, FnBridge, FnMacro}
+    err::{Err, ErrObj, ErrType::{self, *}},
+    fun::{FnNative, Bridge, FnBridge, FnMacro, Callable},
+    gc::{Trace, Tracer}
 };
 
 /// `Jester-rs` representation of data
@@ -14,6 +19,10 @@ pub enum Obj {
     Sym(RcCell<Obj>),
     /// `list`
     Lst(Node),
+    /// `vector`
+    Vct(Node),
+    /// `map`
+    Map(Map),
     /// `float: Primtive + Numeric`
     F64(f64),
     /// `i32: Primtive + Numeric`
@@ -32,13 +41,143 @@ pub enum Obj {
     Bridge(FnBridge),
     /// `macro-fn`
     Macro(FnMacro),
+    /// `error`
+    Error(ErrObj),
     /// `nil`
     Nil(())
 }
 
 use Obj::*;
 
+/// Formats like `Obj::as_string`, but without an `Env`
+/// to name symbols by, so they are written `#<symbol>`
+impl Display for Obj {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.fmt_plain(f, false)
+    }
+}
+
+/// Formats like `Display`, with strings quoted at any depth
+impl Debug for Obj {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.fmt_plain(f, true)
+    }
+}
+
+/// Compares by value, with numbers equal across types
+/// 
+/// ## Note
+/// Unlike `Obj::eq`, values that can't be compared are 
+/// unequal rather than an error. Symbols are equal if they
+/// are the same symbol, functions if they are the same
+/// definition, and bridges if they share their host function
+impl PartialEq for Obj {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Str(x), Str(y))       => x == y,
+            (Bool(x), Bool(y))     => x == y,
+            (Nil(_), Nil(_))       => true,
+            (Sym(x), Sym(y))       => x.raw_eq(y),
+            (Native(x), Native(y)) => x == y,
+            (Macro(x), Macro(y))   => x == y,
+            (Bridge(x), Bridge(y)) => x.same_def(y),
+            (Error(x), Error(y))   => {
+                x.kind() == y.kind() && x.message() == y.message() && x.payload() == y.payload()
+            }
+            (Lst(x), Lst(y)) | (Vct(x), Vct(y)) => {
+                x.len() == y.len() && x
+                    .iter()
+                    .zip(y.iter())
+                    .all(|(x, y)| *x.as_ref() == *y.as_ref())
+            }
+            (Map(x), Map(y)) => {
+                x.len() == y.len() && x
+                    .iter()
+                    .all(|(key, val)| y.get(key) == Some(val))
+            }
+            _ => match (self.is_int(), other.is_int()) {
+                (Ok(x), Ok(y)) => x == y,
+                _ => matches!((self.is_num(), other.is_num()), (Ok(x), Ok(y)) if x == y)
+            }
+        }
+    }
+}
+
+/// ## Note
+/// Bridges are opaque, so what their closures
+/// capture is kept alive
+impl Trace for Obj {
+    fn trace(&self, tracer: &mut Tracer) {
+        match self {
+            Sym(x)     => tracer.cell(x),
+            Lst(x)     => x.trace(tracer),
+            Vct(x)     => x.trace(tracer),
+            Map(x)     => x.trace(tracer),
+            Native(x)  => x.trace(tracer),
+            Macro(x)   => x.trace(tracer),
+            Error(x)   => Trace::trace(x, tracer),
+            _ => ()
+        }
+    }
+}
+
 impl Obj {
+    /// Writes `self` for `Display` and `Debug`, quoting strings if `quote` is set
+    fn fmt_plain(&self, f: &mut Formatter<'_>, quote: bool) -> fmt::Result {
+        let items = |f: &mut Formatter<'_>, node: &Node| {
+            for (i, item) in node.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(" ")?;
+                }
+
+                item.as_ref().fmt_plain(f, quote)?;
+            }
+
+            Ok(())
+        };
+
+        match self {
+            Sym(_) => f.write_str("#<symbol>"),
+            Lst(x) => {
+                f.write_str("(")?;
+                items(f, x)?;
+                f.write_str(")")
+            }
+            Vct(x) => {
+                f.write_str("[")?;
+                items(f, x)?;
+                f.write_str("]")
+            }
+            Map(x) => {
+                f.write_str("{")?;
+
+                for (i, (key, val)) in x.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+
+                    key.as_obj().fmt_plain(f, quote)?;
+                    f.write_str(" ")?;
+                    val.fmt_plain(f, quote)?;
+                }
+
+                f.write_str("}")
+            }
+            Str(x) if quote => write!(f, "\"{}\"", x),
+            Str(x)    => f.write_str(x),
+            F64(x)    => write!(f, "{}", x),
+            I32(x)    => write!(f, "{}", x),
+            I64(x)    => write!(f, "{}", x),
+            I128(x)   => write!(f, "{}", x),
+            Bool(x)   => write!(f, "{}", x),
+            Native(x) => write!(f, "{}()", x.name()),
+            Bridge(x) => write!(f, "{}()", x.name().to_uppercase()),
+            Macro(x)  => write!(f, "{}()", x.name()),
+            Error(x)  => write!(f, "<{} error: {}>", x.kind(), x.message()),
+            Nil(_)    => f.write_str(<()>::type_str())
+        }
+    }
+
 
     /// Evaluates `self`
     pub fn eval(&self, env: &Env) -> Err<Obj> {
@@ -48,10 +187,11 @@ impl Obj {
     /// Assigns value to clone of `other`
     /// 
     /// ## Note
-    /// Panics if not `Sym`
-    pub fn assign(&mut self, other: &Self) {
+    /// Panics if not `Sym`, fails with a `Borrowed`
+    /// error if the value is being read
+    pub fn assign(&self, other: &Self) -> Err {
         match self {
-            Sym(obj) => *obj.as_mut() = other.clone(),
+            Sym(obj) => obj.replace(other.clone()).map(drop),
             _ => panic!("can't assign to non-symbol!")
         }
     }
@@ -59,14 +199,31 @@ impl Obj {
     /// Assigns value to clone of `other` as an `Obj`
     ///     
     /// ## Note
-    /// Panics if not `Sym`
-    pub fn assign_to<T: TypeId>(&mut self, other: T) {
+    /// Panics if not `Sym`, fails with a `Borrowed`
+    /// error if the value is being read
+    pub fn assign_to<T: TypeId>(&self, other: T) -> Err {
         match self {
-            Sym(obj) => *obj.as_mut() = other.as_obj(),
+            Sym(obj) => obj.replace(other.as_obj()).map(drop),
             _ => panic!("can't assign to non-symbol!")
         }
     }
 
+    /// Returns a `MisType` error expecting type `expected`
+    pub fn mis_type(&self, expected: &str) -> ErrType {
+        MisType {
+            expected: expected.to_string(),
+            found: self.type_string()
+        }
+    }
+
+    /// Returns an `ErrCast` error expecting type `expected`
+    pub fn err_cast(&self, expected: &str) -> ErrType {
+        ErrCast {
+            expected: expected.to_string(),
+            found: self.type_string()
+        }
+    }
+
     /// Creates a new `Obj::T`
     pub fn new_value<T: TypeId>(val: T) -> Self {
         val.as_obj()
@@ -87,43 +244,26 @@ impl Obj {
 
     /// Parses an `Obj` literal from a String, returning
     /// `None` if the `str` is not a `Jester-rs` literal 
-    pub fn parse_literal(str: &String) -> Option<Self> {
+    pub fn parse_literal(str: &str) -> Option<Self> {
         if let Ok(num) = Obj::sym_to_num(str) {
             Some(num)
         }
-        else if let Some(str) = Obj::sym_to_str(str) {
-            Some(str)
-        }
         else {
-            None
+            Obj::sym_to_str(str)
         }
     }
 
-    pub fn sym_value(&self) -> Err<&Self> {
+    pub fn sym_value(&self) -> Err<ReadGuard<'_, Self>> {
         match self {
             Sym(sym) => Ok(sym.as_ref()),
-            _ => Err(MisType)
-        }
-    }
-
-    pub fn sym_val_mut(&self) -> Err<&mut Self> {
-        match self {
-            Sym(sym) => Ok(sym.as_mut()),
-            _ => Err(MisType)
+            _ => Err(self.mis_type(RcCell::<Self>::type_str()))
         }
     }
 
-    pub fn val(&self) -> &Self {
-        match self {
-            Sym(sym) => sym.as_ref(),
-            _ => self
-        }
-    }
-
-    pub fn val_mut(&mut self) -> &mut Self {
+    pub fn sym_val_mut(&self) -> Err<WriteGuard<'_, Self>> {
         match self {
             Sym(sym) => sym.as_mut(),
-            _ => self
+            _ => Err(self.mis_type(RcCell::<Self>::type_str()))
         }
     }
 
@@ -132,6 +272,8 @@ impl Obj {
         match self {
             Sym(x)     => x.as_string(env),
             Lst(x)     => x.as_string(env),
+            Vct(x)     => format!("[{}]", x.items_string(env)),
+            Map(x)     => x.as_string(env),
             I32(x)     => x.as_string(env),
             I64(x)     => x.as_string(env),
             I128(x)    => x.as_string(env),
@@ -141,6 +283,7 @@ impl Obj {
             Native(x)  => x.as_string(env),
             Bridge(x)  => x.as_string(env),
             Macro(x)   => x.as_string(env),
+            Error(x)   => x.as_string(env),
             Nil(x)     => x.as_string(env)
         }
     }
@@ -150,6 +293,8 @@ impl Obj {
         match self {
             Sym(_)    => RcCell::<Self>::type_str(),
             Lst(_)    => Node::type_str(),
+            Vct(_)    => Node::VCT_TYPE,
+            Map(_)    => Map::type_str(),
             I32(_)    => i32::type_str(),
             I64(_)    => i64::type_str(),
             I128(_)   => i128::type_str(),
@@ -159,6 +304,7 @@ impl Obj {
             Native(_) => FnNative::type_str(),
             Bridge(_) => FnBridge::type_str(),
             Macro(_)  => FnMacro::type_str(),
+            Error(_)  => ErrObj::type_str(),
             Nil(_)    => <()>::type_str()
         }
         .to_string()
@@ -167,24 +313,20 @@ impl Obj {
     /// Tries to convert `String` into `Obj::Str`
     /// 
     /// ## Example
-    /// ```
+    /// ```text
     /// Str  | "123" 
     /// Str  | "abc" 
     /// None |  123
     /// None |  abc
     /// ```
-    pub fn sym_to_str(src: &String) -> Option<Self> {
+    pub fn sym_to_str(src: &str) -> Option<Self> {
         // asserts if src begins and ends with ""
-        if &src[0..1] != "\"" || &src[src.len() - 1..] != "\"" {
+        if src.len() < 2 || !src.starts_with('"') || !src.ends_with('"') {
             return None
         }
     
         // collects the string content 
-        let col = src
-            .chars()
-            .skip(1)
-            .take(src.len() - 2)
-            .collect::<String>();
+        let col = src[1..src.len() - 1].to_string();
     
         Some(Str(col))
     }
@@ -197,30 +339,30 @@ impl Obj {
     /// 
     /// ## Delimiter
     /// Number can be separated by `_`
-    /// ```
+    /// ```text
     /// i.e. 1_0__0_0__0.
     /// ```
     /// 
     /// ## Binary
     /// Numbers can be interpreted as binary
-    /// ```
+    /// ```text
     /// i.e. #b0001_1111 == 31
     /// ```
     /// 
     /// ## Hexadecimal
     /// Numbers can be interpreted as hexadecimal
-    /// ```
+    /// ```text
     /// i.e. #h6_68A0 == 420_000
     /// ```
-    pub fn sym_to_num(str: &String) -> Err<Self> {
+    pub fn sym_to_num(src: &str) -> Err<Self> {
         // remove all '_'
-        let str: String = str
+        let str: String = src
             .chars()
             .filter(|ch| *ch != '_')
             .collect();
 
         if str.is_empty() {
-            return Err(MisForm)
+            return Err(MisForm(src.to_string()))
         }
 
         // index of first digit
@@ -234,17 +376,12 @@ impl Obj {
         // sign multiplier -/+
         let mut sign = 1;
 
-        let base;
-        if &str[0..1] == "#" {
-            match &str[1..2] {
-                "b" => base = 2,
-                "h" => base = 16,
-                _ => return Err(MisForm)               
-            }
-        }
-        else {
-            base = 10;
-        }
+        let base = match str.strip_prefix('#') {
+            Some(rest) if rest.starts_with('b') => 2,
+            Some(rest) if rest.starts_with('h') => 16,
+            Some(_) => return Err(MisForm(src.to_string())),
+            None => 10
+        };
 
         for (i, ch) in str.chars().enumerate() {
             if fst_dig.is_none() && (ch == '.' || ch.is_digit(base)) {
@@ -255,7 +392,7 @@ impl Obj {
                 match ch {
                     '.' => {
                         if decimal_loc.is_some() || base != 10 {
-                            return Err(MisForm)
+                            return Err(MisForm(src.to_string()))
                         }
 
                         decimal_loc = Some(i);
@@ -266,7 +403,7 @@ impl Obj {
                         }
 
                         if fst_dig.is_some() || sign_loc.is_some() || base != 10 {
-                            return Err(MisForm)
+                            return Err(MisForm(src.to_string()))
                         }
 
                         sign_loc = Some(i);
@@ -276,11 +413,15 @@ impl Obj {
 
                 num_end = i;
             }
+            else if base == 10 || i > 1 {
+                // only the base prefix may be non-numeric
+                return Err(MisForm(src.to_string()))
+            }
         }
 
         // assert a first digit was found
-        if fst_dig.is_none() || (num_end + 1) as usize != str.len() {
-            return Err(MisForm)
+        if fst_dig.is_none() || num_end + 1 != str.len() {
+            return Err(MisForm(src.to_string()))
         }
         
         let fst_dig = fst_dig.unwrap();
@@ -297,7 +438,13 @@ impl Obj {
             .iter()
             .rev()
             .enumerate()
-            .fold(0, |acc, (i, dig)| acc + dig * (base as i128).pow(i as u32));
+            .try_fold(0_i128, |acc, (i, dig)| {
+                (base as i128)
+                    .checked_pow(i as u32)
+                    .and_then(|pow| dig.checked_mul(pow))
+                    .and_then(|dig| acc.checked_add(dig))
+            })
+            .ok_or_else(|| Overflow(src.to_string()))?;
 
 
         if let Some(loc) = decimal_loc {
@@ -313,11 +460,33 @@ impl Obj {
         else if let Ok(int) = i64::try_from(sign * num) {
             Ok(I64(int))
         }
-        else if let Ok(int) = i128::try_from(sign * num) {
-            Ok(I128(int))
-        }
         else {
-            Err(Overflow)
+            Ok(I128(sign * num))
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use crate::core::env::Env;
+
+    #[test]
+    fn bridges_equal_only_their_clones() {
+        let mut env = Env::new().unwrap();
+
+        let one = env.add_bridge("f", |_, _| Ok(1.into())).clone_inner();
+        assert_eq!(one, one.clone());
+        assert_eq!(env.eval_str("f").unwrap(), one);
+
+        // a bridge of the same name in another env runs other code
+        let other = Env::new()
+            .unwrap()
+            .add_bridge("f", |_, _| Ok(2.into()))
+            .clone_inner();
+
+        assert_ne!(one, other);
+
+        let add = env.eval_str("+").unwrap();
+        assert_ne!(add, env.eval_str("-").unwrap());
+        assert_eq!(add, env.eval_str("+").unwrap());
+    }
+}
@@ -1,91 +1,131 @@
 use super::{
     obj::Obj::{self, *},
-    err::{Err, ErrType::*}
+    err::{Err, ErrType::{self, *}}
 };
 
+/// An integer operation at each width, as `checked_*`
+/// methods returning `None` out of range
+struct IntOp {
+    name: &'static str,
+    i32: fn(i32, i32) -> Option<i32>,
+    i64: fn(i64, i64) -> Option<i64>,
+    i128: fn(i128, i128) -> Option<i128>
+}
+
+const ADD: IntOp = IntOp { name: "+", i32: i32::checked_add, i64: i64::checked_add, i128: i128::checked_add };
+const SUB: IntOp = IntOp { name: "-", i32: i32::checked_sub, i64: i64::checked_sub, i128: i128::checked_sub };
+const MUL: IntOp = IntOp { name: "*", i32: i32::checked_mul, i64: i64::checked_mul, i128: i128::checked_mul };
+const DIV: IntOp = IntOp { name: "/", i32: i32::checked_div, i64: i64::checked_div, i128: i128::checked_div };
+const REM: IntOp = IntOp { name: "%", i32: i32::checked_rem, i64: i64::checked_rem, i128: i128::checked_rem };
+
+/// Returns the rank of integer `obj` in the numeric
+/// tower, or `None` if it isn't an integer
+fn width(obj: &Obj) -> Option<u8> {
+    match obj {
+        I32(_)  => Some(0),
+        I64(_)  => Some(1),
+        I128(_) => Some(2),
+        _ => None
+    }
+}
+
 impl Obj {
-    pub fn add(&mut self, other: Obj) -> Err {
-        match self {
-            I32(x)  => *x += other.as_i32()?,
-            I64(x)  => *x += other.as_i64()?,
-            I128(x) => *x += other.as_i128()?,
-            F64(x)  => *x += other.as_f64()?,
-            _ => return Err(MisType)
+    fn mis_comp(&self, other: &Obj) -> ErrType {
+        MisComp { 
+            lhs: self.type_string(), 
+            rhs: other.type_string() 
         }
-
-        Ok(())
     }
 
-    pub fn sub(&mut self, other: Obj) -> Err {
-        match self {
-            I32(x)  => *x -= other.as_i32()?,
-            I64(x)  => *x -= other.as_i64()?,
-            I128(x) => *x -= other.as_i128()?,
-            F64(x)  => *x -= other.as_f64()?,
-            _ => return Err(MisType)
+    /// Sets `self` to `op` of `self` and `rhs`, both integers, run
+    /// at the wider of their widths and widened again while out
+    /// of range, erroring once out of range of `i128`
+    fn int_op(&mut self, rhs: &Obj, op: &IntOp) -> Err {
+        let width = width(self).max(width(rhs));
+
+        if width <= Some(0) {
+            if let Some(res) = (op.i32)(self.as_i32()?, rhs.as_i32()?) {
+                *self = I32(res);
+                return Ok(())
+            }
         }
 
-        Ok(())
-    }
+        if width <= Some(1) {
+            if let Some(res) = (op.i64)(self.as_i64()?, rhs.as_i64()?) {
+                *self = I64(res);
+                return Ok(())
+            }
+        }
 
-    pub fn neg(&mut self) -> Err {
-        match self {
-            I32(x)  => *x *= -1,
-            I64(x)  => *x *= -1,
-            I128(x) => *x *= -1,
-            F64(x)  => *x *= -1.,
-            _ => return Err(MisType)
+        let (x, y) = (self.as_i128()?, rhs.as_i128()?);
+
+        match (op.i128)(x, y) {
+            Some(res) => *self = I128(res),
+            None if y == 0 => return Err(DivZero),
+            None => return Err(NumOverflow(format!("{} {} {}", x, op.name, y)))
         }
 
         Ok(())
     }
 
-    pub fn mul(&mut self, other: Obj) -> Err {
+    /// Runs `op` on integer `self` and `other`, cast to the type
+    /// of `self` unless an integer, or `float` on a float `self`
+    fn arith(&mut self, other: Obj, op: &IntOp, float: fn(&mut f64, f64)) -> Err {
         match self {
-            I32(x)  => *x *= other.as_i32()?,
-            I64(x)  => *x *= other.as_i64()?,
-            I128(x) => *x *= other.as_i128()?,
-            F64(x)  => *x *= other.as_f64()?,
-            _ => return Err(MisType)
+            F64(x) => float(x, other.as_f64()?),
+
+            I32(_) | I64(_) | I128(_) => {
+                let rhs = match (&*self, width(&other)) {
+                    (_, Some(_))   => other,
+                    (I32(_), None) => I32(other.as_i32()?),
+                    (I64(_), None) => I64(other.as_i64()?),
+                    _ => I128(other.as_i128()?)
+                };
+
+                self.int_op(&rhs, op)?
+            }
+
+            _ => return Err(self.mis_type("number"))
         }
 
         Ok(())
     }
 
-    pub fn div(&mut self, other: Obj) -> Err {
-        match self {
-            I32(x)  => *x /= other.as_i32()?,
-            I64(x)  => *x /= other.as_i64()?,
-            I128(x) => *x /= other.as_i128()?,
-            F64(x)  => *x /= other.as_f64()?,
-            _ => return Err(MisType)
-        }
+    pub fn add(&mut self, other: Obj) -> Err {
+        self.arith(other, &ADD, |x, y| *x += y)
+    }
 
-        Ok(())
+    pub fn sub(&mut self, other: Obj) -> Err {
+        self.arith(other, &SUB, |x, y| *x -= y)
     }
 
-    pub fn modulos(&mut self, other: Obj) -> Err {
-        match self {
-            I32(x)  => *x %= other.as_i32()?,
-            I64(x)  => *x %= other.as_i64()?,
-            I128(x) => *x %= other.as_i128()?,
-            F64(x)  => *x %= other.as_f64()?,
-            _ => return Err(MisType)
-        }
+    pub fn neg(&mut self) -> Err {
+        self.arith(I32(-1), &MUL, |x, _| *x *= -1.)
+    }
 
-        Ok(())
+    pub fn mul(&mut self, other: Obj) -> Err {
+        self.arith(other, &MUL, |x, y| *x *= y)
+    }
+
+    pub fn div(&mut self, other: Obj) -> Err {
+        self.arith(other, &DIV, |x, y| *x /= y)
+    }
+
+    pub fn modulos(&mut self, other: Obj) -> Err {
+        self.arith(other, &REM, |x, y| *x %= y)
     }
 
     pub fn eq(&self, other: &Obj) -> Err<bool> {
         match (self, other) {
             (Bool(b1), Bool(b2)) => Ok(b1 == b2),
             (Str(s1), Str(s2))   => Ok(s1 == s2),     
+            (Map(m1), Map(m2))   => m1.eq(m2),
             _ => {
                 match (self.is_num(), other.is_num()) {
                     (Ok(n1), Ok(n2)) => {
                         Ok(n1 == n2)
                     }
-                    _ => Err(MisComp)
+                    _ => Err(self.mis_comp(other))
                 }
             }
         }
@@ -94,14 +134,60 @@ impl Obj {
     pub fn le(&self, other: &Obj) -> Err<bool> {
         match (self.is_num(), other.is_num()) {
             (Ok(n1), Ok(n2)) => Ok(n1 < n2),
-            _ => Err(MisComp)
+            _ => Err(self.mis_comp(other))
         }
     }
 
     pub fn le_eq(&self, other: &Obj) -> Err<bool> {
         match (self.is_num(), other.is_num()) {
             (Ok(n1), Ok(n2)) => Ok(n1 <= n2),
-            _ => Err(MisComp)
+            _ => Err(self.mis_comp(other))
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use crate::core::env::Env;
+
+    /// Returns the kind of the error `src` fails with
+    fn fails(src: &str) -> String {
+        let env = Env::new().unwrap();
+        env.eval_str(src).unwrap_err().kind().to_string()
+    }
+
+    /// Returns the value of `src` and its type
+    fn eval(src: &str) -> String {
+        let env = Env::new().unwrap();
+        let obj = env.eval_str(src).unwrap();
+
+        format!("{} {}", obj.display(&env), obj.type_string())
+    }
+
+    #[test]
+    fn int_division_by_zero_fails() {
+        assert_eq!(fails("(/ 1 0)"), "div-by-zero");
+        assert_eq!(fails("(% 1 0)"), "div-by-zero");
+        assert_eq!(fails("(/ 5000000000 0)"), "div-by-zero");
+    }
+
+    #[test]
+    fn int_overflow_widens() {
+        assert_eq!(eval("(+ 2147483647 1)"), "2147483648 i64");
+        assert_eq!(eval("(- -2147483648 1)"), "-2147483649 i64");
+        assert_eq!(eval("(* 2147483647 2)"), "4294967294 i64");
+        assert_eq!(eval("(- -2147483648)"), "2147483648 i64");
+        assert_eq!(eval("(/ -2147483648 -1)"), "2147483648 i64");
+        assert_eq!(eval("(* 4294967296 4294967296)"), "18446744073709551616 i128");
+        assert_eq!(eval("(+ 1 5000000000)"), "5000000001 i64");
+        assert_eq!(eval("(+ 1 2)"), "3 i32");
+    }
+
+    #[test]
+    fn int_overflow_past_i128_fails() {
+        let max = i128::MAX;
+
+        assert_eq!(fails(&format!("(+ {} 1)", max)), "overflow");
+        assert_eq!(fails(&format!("(* {} 2)", max)), "overflow");
+        assert_eq!(fails(&format!("(- (- {}) 2)", max)), "overflow");
+    }
+}
@@ -1,48 +1,61 @@
 use super::{
     node::Node, 
+    rc_cell::RcCell,
+    sync::Shared,
     obj::Obj, 
     type_id::TypeId,
     env::Env, 
-    err::Err,
+    err::{Err, ErrType::{Unbalanced, Denied}},
     lex::{
         Tok,
+        Span,
         Expr,
+        Delim,
         CONTROLS,
         OPERATORS,
-        METAS,
         TokType::{self, *}
     }
 };
 
 struct Lexer {
+    file: Shared<str>,
     toks: Vec<Tok>,
-    exprs: Vec<Expr>
+    exprs: Vec<Expr>,
+    /// index of the next `Tok` to read
+    pos: usize
 }
 
 impl Lexer {
-    pub fn new(env: &mut Env, src: &String) -> Err<Obj> {
+    pub fn run(env: &Env, src: &str, file: &str) -> Err<Obj> {
         let mut lexer = Lexer {
+            file: Shared::from(file),
             toks: Vec::new(),
             exprs: Vec::new(),
+            pos: 0
         };
 
         lexer.get_toks(src);
+        lexer.check_delims()?;
         lexer.get_exprs();
         lexer.expand_ops();
+        // expanding quotes must leave the brackets balanced
+        lexer.check_delims()?;
 
-        let tree = lexer.to_syntax_tree(env);
+        let mut res = Obj::Nil(());
 
-        tree
-            .iter()
-            .progn(|obj| env.eval(obj.as_ref()))
-    }
+        while let Some(tree) = lexer.next_tree(env)? {
+            res = env
+                .eval(&tree.clone_inner())
+                .map_err(|err| env.trace(err))?;
 
-    fn add_tok(&mut self, tok_type: TokType) {
-        let tok = Tok {
-            tok_type,
-            id: self.toks.len()
-        };
+            env.collect_if_due();
+        }
+
+        Ok(res)
+    }
 
+    fn add_tok(&mut self, tok_type: TokType, span: Span) {
+        let tok = tok_type.into_tok(self.toks.len(), span);
         self.toks.push(tok);
     }
 
@@ -51,33 +64,56 @@ impl Lexer {
     /// ## Note
     /// - A special character is either a `delimeter` or `operator`
     /// - each special character is exactly 1 character
+    /// - each `Tok` records the `Span` it was read from
     /// 
     /// ## Explanation
     /// for each character:
-    /// ```
+    /// ```text
     /// if delimeter or operator:
     /// ``` 
     /// - push buffer then special character as `Tok`s 
     /// - reset buffer
-    /// ```
+    /// ```text
     /// else:
     /// ```
     /// - push character to current buffer
-    fn get_toks(&mut self, src: &String) {
+    fn get_toks(&mut self, src: &str) {
         // lexical buffer
         let mut lex = String::new();
+        // span of the lexical buffer
+        let mut lex_span = None;
         // whether parsing comment
         let mut com = false;
         // whether parsing string
         let mut str  = false;
+        // current line and column
+        let mut line = 1;
+        let mut col = 0;
+
+        for (i, ch) in src.char_indices() {  
+            if ch == '\n' {
+                line += 1;
+                col = 0;
+            }
+            else {
+                col += 1;
+            }
+
+            let span = Span {
+                file: self.file.clone(),
+                line,
+                col,
+                beg: i,
+                end: i + ch.len_utf8()
+            };
 
-        for (i, ch) in src.chars().enumerate() {                             
             match ch {
                 '\n' if !str => com = false,
                 ';' if !str => com = !com,
                 '"' if !com => {     
                     if str {
                         lex.push(ch);
+                        lex_span = lex_span.map(|beg: Span| beg.to(&span));
                     }
                     str = !str;
                 }   
@@ -88,37 +124,69 @@ impl Lexer {
                 continue;
             }
 
-            let meta = METAS.contains(&ch);
             let op = OPERATORS.contains(&ch);
             let cntrl = CONTROLS.contains(&ch);
 
-            if !str && (cntrl || op || meta) {     
-                if !lex.is_empty() {
-                    self.add_tok(Sym(lex.clone()));
+            if !str && (cntrl || op) {     
+                if let Some(lex_span) = lex_span.take() {
+                    self.add_tok(Sym(lex.clone()), lex_span);
                     lex.clear();
                 }
 
-                if meta {
-                    self.add_tok(Sym(ch.to_string()));
-                    continue;
-                }
-
                 match ch {
-                    '('  => self.add_tok(Beg),   
-                    ')'  => self.add_tok(End),
-                    ','  => self.add_tok(Esc),     
-                    '\'' => self.add_tok(Qte), 
+                    '('  => self.add_tok(Beg(Delim::Paren), span),   
+                    ')'  => self.add_tok(End(Delim::Paren), span),
+                    '['  => self.add_tok(Beg(Delim::Bracket), span),   
+                    ']'  => self.add_tok(End(Delim::Bracket), span),
+                    '{'  => self.add_tok(Beg(Delim::Brace), span),   
+                    '}'  => self.add_tok(End(Delim::Brace), span),
+                    ','  => self.add_tok(Esc, span),     
+                    '\'' => self.add_tok(Qte, span), 
                     _ => ()
                 }
             }
             else {
                 lex.push(ch);
 
-                if !lex.is_empty() && i + 1 == src.len() {
-                    self.add_tok(Sym(lex.clone()));                
+                lex_span = match lex_span {
+                    Some(beg) => Some(beg.to(&span)),
+                    None => Some(span)
+                };
+            }
+        }
+
+        if let Some(lex_span) = lex_span {
+            self.add_tok(Sym(lex), lex_span);
+        }
+    }
+
+    /// Checks that each bracket is closed by its match,
+    /// erroring at the first one that isn't
+    fn check_delims(&self) -> Err {
+        let mut open: Vec<(Delim, &Span)> = Vec::new();
+
+        for tok in self.toks.iter() {
+            match &tok.tok_type {
+                Beg(delim) => open.push((*delim, &tok.span)),
+                End(delim) => {
+                    let end = delim.brackets().1;
+
+                    let err = match open.pop() {
+                        None => format!("unexpected '{}'", end),
+                        Some((open, _)) if open != *delim => format!("'{}' closed by '{}'", open.brackets().0, end),
+                        Some(_) => continue
+                    };
+
+                    return Err(Unbalanced(err).at(&tok.span))
                 }
+                _ => ()
             }
         }
+
+        match open.pop() {
+            Some((delim, span)) => Err(Unbalanced(format!("unclosed '{}'", delim.brackets().0)).at(span)),
+            None => Ok(())
+        }
     }
 
     fn get_exprs(&mut self) {
@@ -129,7 +197,7 @@ impl Lexer {
 
         for tok in self.toks.iter() {
             match tok.tok_type {
-                Beg => {
+                Beg(delim) => {
                     self.exprs.push(
                         Expr { 
                             beg_id: tok.id as isize, 
@@ -137,13 +205,14 @@ impl Lexer {
                             elems: Vec::new(),
                             esc,
                             qte,
+                            delim
                     });
 
                     qte = false;
                     esc = false;
                 }
 
-                End => {
+                End(_) => {
                     self.exprs.last_mut().unwrap().end_id = tok.id as isize;
 
                     if self.exprs.len() > 1 {
@@ -163,7 +232,8 @@ impl Lexer {
                         end_id: tok.id as isize,
                         elems: Vec::new(),
                         esc,
-                        qte
+                        qte,
+                        delim: Delim::default()
                     };
 
                     if self.exprs.is_empty() {
@@ -197,7 +267,8 @@ impl Lexer {
                             return false;
                         }
 
-                        if !j.any(|k| k.esc) {
+                        // quoted vector and map literals quote their items instead
+                        if !j.any(|k| k.esc) && j.delim == Delim::Paren {
                             // expand quote for expression j
                             let beg = self.toks
                                 .iter()
@@ -210,14 +281,29 @@ impl Lexer {
                                 .unwrap();
 
                             let id = self.toks.len();
+                            let beg_span = self.toks[beg].span.clone();
+                            let end_span = self.toks[end].span.clone();
 
-                            self.toks.insert(end + 1, End.to_tok(id + 0));
-                            self.toks.insert(beg, Sym("quote".to_string()).to_tok(id + 1));
-                            self.toks.insert(beg, Beg.to_tok(id + 2));
+                            self.toks.insert(end + 1, End(Delim::Paren).into_tok(id, end_span));
+                            self.toks.insert(beg, Sym("quote".to_string()).into_tok(id + 1, beg_span.clone()));
+                            self.toks.insert(beg, Beg(Delim::Paren).into_tok(id + 2, beg_span));
 
                             return false;
                         }
 
+                        // a list with escapes is built by a call to `list`,
+                        // while a symbol (a single token) is left as it is
+                        if j.delim == Delim::Paren && j.beg_id != j.end_id {
+                            for tok in self.toks.iter_mut() {
+                                if tok.id as isize == j.beg_id {
+                                    tok.tok_type = Beg(Delim::Quasi);
+                                }
+                                if tok.id as isize == j.end_id {
+                                    tok.tok_type = End(Delim::Quasi);
+                                }
+                            }
+                        }
+
                         true
                     })
                 }
@@ -225,7 +311,8 @@ impl Lexer {
         }
     }
 
-    /// Convert `Vec<Tok>` into syntax tree
+    /// Converts the next top-level expression of `Vec<Tok>` 
+    /// into a syntax tree, returning `None` once all are read
     /// 
     /// ## Note
     /// `Jester-rs` represents code as recursive linked lists of `Obj`
@@ -233,82 +320,135 @@ impl Lexer {
     ///
     /// ## Example
     /// 
-    /// ```
+    /// ```text
     /// 1: (set x (+ 5 5))
     /// 2: (= x 10)   
     /// 3: 
     /// 4: x 
     /// ```
-    /// translates to...
+    /// is read as three expressions...
     ///
+    /// ```text
+    /// (...) --> 'set' --> 'x' --> (...)
+    ///                               \__ '+' --> '5' --> '5'
+    /// (...) --> '=' --> 'x' --> '10'
+    /// 'x'
     /// ```
-    /// (...) --> (...) --> 'x'
-    ///   \         \__ '=' --> 'x' --> '10'
-    ///    \ 
-    ///     \__ 'set' --> 'x' --> (...)
-    ///                             \__ '+' --> '5' --> '5'
-    /// ```
-    fn to_syntax_tree(&mut self, env: &mut Env) -> Node {
+    /// 
+    /// each list `Node` is tagged with the `Span` from its 
+    /// opening to its closing parenthesis, and vector `[..]`
+    /// and map `{..}` literals become calls to `vector` and `hash-map`,
+    /// and quoted lists with escapes calls to `list`
+    /// 
+    /// Symbols are bound when read, so an expression sees
+    /// the modules defined and imported by those before it
+    fn next_tree(&mut self, env: &Env) -> Err<Option<RcCell<Obj>>> {
         let mut cur_node = Node::default();
         let mut pre_node = Vec::new();
-        let mut depth: isize = 0;
+        let mut pre_span = Vec::new();
         
-        for tok in self.toks.iter() {
+        while let Some(tok) = self.toks.get(self.pos) {
+            self.pos += 1;
+
             match &tok.tok_type {
-                Beg => {
-                    depth += 1;
-                    
+                Beg(delim) => {
                     pre_node.push(cur_node);     
+                    pre_span.push(tok.span.clone());
                     cur_node = Node::default();
-                }
 
-                End => {
-                    depth -= 1;
-                    if depth < 0 {
-                        panic!("too many left parentheses!");
-                    }
-                    
-                    if let Some(mut parent) = pre_node.pop() {
-                        let obj = env.gen_sym(cur_node.as_obj());
-                        parent.push(obj.into());
-                        cur_node = parent;
+                    // the bridge itself, so params can't shadow it
+                    if let Some(head) = delim.head() {
+                        let head = env
+                            .get_sym(head)
+                            .ok_or_else(|| Denied(head.to_string()).at(&tok.span))?;
+
+                        cur_node.push(head.clone_inner().into());
                     }
                 }
 
-                Sym(sym) => {
-                    match Obj::parse_literal(sym) {
-                        Some(literal) => cur_node.push(literal.into()),
+                End(delim) => {
+                    let (mut parent, beg) = match (pre_node.pop(), pre_span.pop()) {
+                        (Some(parent), Some(beg)) => (parent, beg),
                         _ => {
-                            if !env.has_sym(&sym) {
-                                env.add_sym(&sym, Obj::Nil(()));
-                            }
+                            let err = format!("unexpected '{}'", delim.brackets().1);
+                            return Err(Unbalanced(err).at(&tok.span))
+                        }
+                    };
+
+                    cur_node.set_span(beg.to(&tok.span));
+
+                    let obj = RcCell::from(cur_node.as_obj());
+
+                    if pre_node.is_empty() {
+                        return Ok(Some(obj))
+                    }
+
+                    parent.push(obj);
+                    cur_node = parent;
+                }
 
-                            let obj = env.get_sym(&sym).unwrap();
-                            cur_node.push(Obj::Sym(obj).into()); 
+                Sym(sym) => {
+                    let obj = match Obj::parse_literal(sym) {
+                        Some(literal) => literal.into(),
+                        None => {
+                            let obj = env
+                                .intern(sym)
+                                .map_err(|err| err.at(&tok.span))?;
+
+                            Obj::Sym(obj).into()
                         }
+                    };
+
+                    if pre_node.is_empty() {
+                        return Ok(Some(obj))
                     }
+
+                    cur_node.push(obj);
                 }
                 
                 _ => ()
             }
         }
 
-        if depth != 0 {
-            panic!("imbalanced parentheses!")
-        }
-
-        cur_node
+        Ok(None)
     }
 
 }
 
+/// Source name given to code without a file
+const STRING_SRC: &str = "<string>";
+
 impl Env {
-    pub fn add_from_string(&mut self, src: &str) -> Err<Obj> {
-        Lexer::new(self, &src.to_string())
+    pub fn add_from_string(&self, src: &str) -> Err<Obj> {
+        self.eval_source(src, STRING_SRC)
     }
     
-    pub fn add_from_file(&mut self, path: &str) -> Err<Obj> {
-        let src = std::fs::read_to_string(path.to_string()).expect("couldn't read file!");
-        Lexer::new(self, &src)
+    pub fn add_from_file(&self, path: &str) -> Err<Obj> {
+        self.eval_file(path)
+    }
+
+    /// Evaluates `src`, read from `file`
+    pub(crate) fn eval_source(&self, src: &str, file: &str) -> Err<Obj> {
+        self.metered(|| Lexer::run(self, src, file))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use crate::core::env::Env;
+
+    #[test]
+    fn unbalanced_input_fails() {
+        let env = Env::new().unwrap();
+
+        for src in ["(+ 1", ")", "(]", "[1 2)", "'(a ,b"] {
+            let err = env.eval_str(src).unwrap_err();
+            assert_eq!(err.kind(), "unbalanced", "{}", src);
+        }
+    }
+
+    #[test]
+    fn escape_after_quoted_symbol_reads() {
+        let env = Env::new().unwrap();
+        assert!(env.eval_str("'a ,b").is_ok());
+    }
+}
@@ -1,62 +1,83 @@
-use std::{
-    cell::RefCell, 
-    rc::Rc
+use super::{
+    gc::Track,
+    sync::{Shared, Weak, Lock, ReadGuard, WriteGuard},
+    err::{Err, ErrType::Borrowed}
 };
 
+/// A shared mutable cell, such as the value of a symbol
+/// 
+/// ## Note
+/// Reads and writes are checked at runtime. A write fails with
+/// a `Borrowed` error while the value is being read, and writes
+/// never run code, so a read can't find the value being written.
+/// With the `sync` feature, a read waits for a write on another
+/// thread, while a write fails if another thread is reading
 pub struct RcCell<T> {
-    raw: Rc<RefCell<T>>
+    raw: Shared<Lock<T>>
 }
 
 impl<T> Clone for RcCell<T> {
     fn clone(&self) -> Self {
         Self { 
-            raw: Rc::clone(&self.raw)
+            raw: Shared::clone(&self.raw)
         }
     }
 }
 
-impl<T> From<T> for RcCell<T> {
+impl<T: Track> From<T> for RcCell<T> {
     fn from(raw: T) -> Self {
+        let raw = Shared::new(Lock::new(raw));
+        T::track(&raw);
+
         Self { 
-            raw: Rc::new(RefCell::new(raw))
+            raw
         }
     }
 }
 
-impl<T> From<Rc<RefCell<T>>> for RcCell<T> {
-    fn from(raw: Rc<RefCell<T>>) -> Self {
+impl<T> From<Shared<Lock<T>>> for RcCell<T> {
+    fn from(raw: Shared<Lock<T>>) -> Self {
         Self { 
-            raw: raw
+            raw
         }
     }
 }
 
 impl<T: PartialEq> PartialEq for RcCell<T> {
     fn eq(&self, other: &Self) -> bool {
-        self.raw == other.raw
+        self.raw_eq(other) || *self.as_ref() == *other.as_ref()
     }
 }
 
 impl<T> RcCell<T> {
-    pub fn as_ref(&self) -> &T {
-        unsafe {
-            self.raw
-                .as_ptr()
-                .as_ref()
-                .unwrap()
-        }    
+    /// Returns the value
+    /// 
+    /// ## Note
+    /// Panics if the value is being written, which can't happen
+    /// while evaluating, as writes are held only while storing
+    #[allow(clippy::should_implement_trait)]
+    pub fn as_ref(&self) -> ReadGuard<'_, T> {
+        self.raw.read()
     }
 
-    pub fn as_mut(&self) -> &mut T {
-        unsafe {
-            self.raw
-                .as_ptr()
-                .as_mut()
-                .unwrap()
-        }
+    /// Returns the value to write in place, or a `Borrowed` 
+    /// error if it is being read, i.e. a list being iterated
+    /// 
+    /// ## Note
+    /// No code may run, and `self` may not be read, until 
+    /// the returned guard is dropped
+    pub fn as_mut(&self) -> Err<WriteGuard<'_, T>> {
+        self.raw
+            .try_write()
+            .ok_or(Borrowed)
+    }
+
+    /// Replaces the value, returning the previous
+    pub fn replace(&self, val: T) -> Err<T> {
+        Ok(std::mem::replace(&mut *self.as_mut()?, val))
     }
 
-    pub fn as_raw(&self) -> &Rc<RefCell<T>> {
+    pub fn as_raw(&self) -> &Shared<Lock<T>> {
         &self.raw
     }
 
@@ -67,7 +88,11 @@ impl<T> RcCell<T> {
         self.as_ref().clone()
     }
 
+    pub fn downgrade(&self) -> Weak<Lock<T>> {
+        Shared::downgrade(&self.raw)
+    }
+
     pub fn raw_eq(&self, other: &Self) -> bool {
-        self.as_raw().as_ptr() == other.as_raw().as_ptr()
+        Shared::ptr_eq(&self.raw, &other.raw)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,153 @@
+use super::{
+    obj::Obj,
+    err::Err,
+    rc_cell::RcCell,
+    gc::{Trace, Tracer}
+};
+
+/// A lexical frame binding symbols to values
+/// 
+/// ## Note
+/// Symbols are keyed by their global `RcCell`, so a binding
+/// shadows the global value of the same symbol. Frames are 
+/// shared, letting closures keep the frames they were made in
+/// 
+/// ## Example
+/// ```text
+/// (let (a 10)        ; frame { a: 10 } -> global
+///     (let (b 20)    ; frame { b: 20 } -> frame { a: 10 } -> global
+///         (+ a b)))
+/// ```
+#[derive(Default)]
+pub struct Scope {
+    vars: Vec<(RcCell<Obj>, RcCell<Obj>)>,
+    parent: Option<RcCell<Scope>>,
+    /// whether bindings are still being added, as by `let`
+    binding: bool
+}
+
+impl Scope {
+    pub fn new(parent: Option<RcCell<Scope>>) -> Self {
+        Self {
+            vars: Vec::new(),
+            parent,
+            binding: false
+        }
+    }
+
+    /// Creates a frame whose bindings are made one at a time,
+    /// where each may run code, until `seal` is called
+    pub fn binding(parent: Option<RcCell<Scope>>) -> Self {
+        Self {
+            binding: true,
+            ..Self::new(parent)
+        }
+    }
+
+    /// Marks every binding of this frame as made
+    pub fn seal(&mut self) {
+        self.binding = false;
+    }
+
+    /// Returns whether this frame, or one it is nested
+    /// in, may still bind symbols it doesn't bind yet
+    pub fn is_binding(&self) -> bool {
+        self.binding || self.parent
+            .as_ref()
+            .is_some_and(|parent| parent.as_ref().is_binding())
+    }
+
+    /// Binds `sym` to `val` in this frame
+    /// 
+    /// ## Note
+    /// Rebinding `sym` fails with a `Borrowed`
+    /// error if its value is being read
+    pub fn bind(&mut self, sym: &RcCell<Obj>, val: Obj) -> Err {
+        match self.vars.iter().find(|(rhs, _)| sym.raw_eq(rhs)) {
+            Some((_, cell)) => { cell.replace(val)?; }
+            None => self.vars.push((sym.clone(), RcCell::from(val)))
+        }
+
+        Ok(())
+    }
+
+    /// Returns the cell of the `i`th binding in this frame
+    /// 
+    /// ## Note
+    /// Panics if out of bounds
+    pub fn slot(&self, i: usize) -> &RcCell<Obj> {
+        &self.vars[i].1
+    }
+
+    /// Returns the cell bound to `sym` in the innermost frame 
+    /// defining it, or `None` if `sym` is unbound
+    pub fn lookup(&self, sym: &RcCell<Obj>) -> Option<RcCell<Obj>> {
+        let local = self.vars
+            .iter()
+            .find(|(rhs, _)| sym.raw_eq(rhs))
+            .map(|(_, cell)| cell.clone());
+
+        match (local, &self.parent) {
+            (Some(cell), _) => Some(cell),
+            (None, Some(parent)) => parent.as_ref().lookup(sym),
+            (None, None) => None
+        }
+    }
+}
+
+impl Trace for Scope {
+    fn trace(&self, tracer: &mut Tracer) {
+        for (sym, cell) in &self.vars {
+            tracer.cell(sym);
+            tracer.cell(cell);
+        }
+
+        if let Some(parent) = &self.parent {
+            tracer.cell(parent);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::env::Env;
+
+    fn eval(src: &str) -> String {
+        let env = Env::new().unwrap();
+        let obj = env.eval_str(src).unwrap();
+
+        obj.display(&env)
+    }
+
+    #[test]
+    fn closures_keep_their_own_frames() {
+        let src = "
+            (defun make-counter () (let (n 0) (lambda () (set n (+ n 1)) n)))
+            (set c1 (make-counter))
+            (set c2 (make-counter))
+            (list (c1) (c1) (c2))";
+
+        assert_eq!(eval(src), "(1 2 1)");
+    }
+
+    #[test]
+    fn closures_see_their_defining_scope() {
+        let src = "
+            (set adder (lambda (a) (lambda (b) (+ a b))))
+            (set add5 (adder 5))
+            (let (a 100) (list ((adder 3) 4) (add5 1)))";
+
+        assert_eq!(eval(src), "(7 6)");
+    }
+
+    #[test]
+    fn callers_bindings_stay_hidden() {
+        let src = "
+            (set y 1)
+            (defun get-y () y)
+            (defun shadow (y) (get-y))
+            (shadow 2)";
+
+        assert_eq!(eval(src), "1");
+    }
+}
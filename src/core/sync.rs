@@ -0,0 +1,168 @@
+//! Shared pointers and locks the core types are built on
+//!
+//! ## Note
+//! By default these are `Rc` and `RefCell`, so an `Env` and its
+//! values stay on the thread that made them. With the `sync` feature
+//! they are `Arc` and `RwLock`, making `Env`, `Obj` and `RcCell`
+//! `Send + Sync`, and bridges must then be `Send + Sync` as well
+
+#[cfg(not(feature = "sync"))]
+pub use std::rc::{Rc as Shared, Weak};
+
+#[cfg(feature = "sync")]
+pub use std::sync::{Arc as Shared, Weak};
+
+#[cfg(not(feature = "sync"))]
+use std::cell::{Ref, RefCell, RefMut};
+
+#[cfg(feature = "sync")]
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
+
+/// A value computed once, then shared
+#[cfg(not(feature = "sync"))]
+pub type Once<T> = std::cell::OnceCell<T>;
+
+/// A value computed once, then shared
+#[cfg(feature = "sync")]
+pub type Once<T> = std::sync::OnceLock<T>;
+
+/// Guard of a `Lock` being read
+#[cfg(not(feature = "sync"))]
+pub type ReadGuard<'a, T> = Ref<'a, T>;
+
+/// Guard of a `Lock` being read
+#[cfg(feature = "sync")]
+pub type ReadGuard<'a, T> = RwLockReadGuard<'a, T>;
+
+/// Guard of a `Lock` being written
+#[cfg(not(feature = "sync"))]
+pub type WriteGuard<'a, T> = RefMut<'a, T>;
+
+/// Guard of a `Lock` being written
+#[cfg(feature = "sync")]
+pub type WriteGuard<'a, T> = RwLockWriteGuard<'a, T>;
+
+/// Bound on values captured by bridges: `Send + Sync`
+/// with the `sync` feature, and any type otherwise
+#[cfg(not(feature = "sync"))]
+pub trait ThreadSafe {}
+
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> ThreadSafe for T {}
+
+/// Bound on values captured by bridges: `Send + Sync`
+/// with the `sync` feature, and any type otherwise
+#[cfg(feature = "sync")]
+pub trait ThreadSafe: Send + Sync {}
+
+#[cfg(feature = "sync")]
+impl<T: ?Sized + Send + Sync> ThreadSafe for T {}
+
+/// A value read by many or written by one at a time
+///
+/// ## Note
+/// `read` and `write` wait for other threads, but panic (or
+/// deadlock, with the `sync` feature) if the same thread holds
+/// a conflicting guard. `try_read` and `try_write` return `None`
+/// instead of waiting
+#[derive(Default)]
+pub struct Lock<T> {
+    #[cfg(not(feature = "sync"))]
+    raw: RefCell<T>,
+    #[cfg(feature = "sync")]
+    raw: RwLock<T>
+}
+
+impl<T: Clone> Clone for Lock<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.read().clone())
+    }
+}
+
+#[cfg(not(feature = "sync"))]
+impl<T> Lock<T> {
+    pub fn new(val: T) -> Self {
+        Self {
+            raw: RefCell::new(val)
+        }
+    }
+
+    pub fn read(&self) -> ReadGuard<'_, T> {
+        self.raw.borrow()
+    }
+
+    pub fn write(&self) -> WriteGuard<'_, T> {
+        self.raw.borrow_mut()
+    }
+
+    pub fn try_read(&self) -> Option<ReadGuard<'_, T>> {
+        self.raw.try_borrow().ok()
+    }
+
+    pub fn try_write(&self) -> Option<WriteGuard<'_, T>> {
+        self.raw.try_borrow_mut().ok()
+    }
+
+    /// Returns the value without locking, as `self` is borrowed mutably
+    pub fn get_mut(&mut self) -> &mut T {
+        self.raw.get_mut()
+    }
+}
+
+/// ## Note
+/// A thread panicking while holding a guard leaves the value
+/// as it was, since writes never run code, so poisoning is ignored
+#[cfg(feature = "sync")]
+impl<T> Lock<T> {
+    pub fn new(val: T) -> Self {
+        Self {
+            raw: RwLock::new(val)
+        }
+    }
+
+    pub fn read(&self) -> ReadGuard<'_, T> {
+        self.raw
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn write(&self) -> WriteGuard<'_, T> {
+        self.raw
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn try_read(&self) -> Option<ReadGuard<'_, T>> {
+        match self.raw.try_read() {
+            Ok(guard) => Some(guard),
+            Err(TryLockError::Poisoned(err)) => Some(err.into_inner()),
+            Err(TryLockError::WouldBlock) => None
+        }
+    }
+
+    pub fn try_write(&self) -> Option<WriteGuard<'_, T>> {
+        match self.raw.try_write() {
+            Ok(guard) => Some(guard),
+            Err(TryLockError::Poisoned(err)) => Some(err.into_inner()),
+            Err(TryLockError::WouldBlock) => None
+        }
+    }
+
+    /// Returns the value without locking, as `self` is borrowed mutably
+    pub fn get_mut(&mut self) -> &mut T {
+        self.raw
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// fails to build if a core type isn't shareable across threads
+#[cfg(feature = "sync")]
+const _: fn() = || {
+    fn thread_safe<T: Send + Sync>() {}
+
+    thread_safe::<super::env::Env>();
+    thread_safe::<super::obj::Obj>();
+    thread_safe::<super::err::ErrType>();
+    thread_safe::<super::rc_cell::RcCell<super::obj::Obj>>();
+};
@@ -1,19 +1,38 @@
 use super::{
     node::Node,
+    map::Map,
     rc_cell::RcCell,
+    limit,
     obj::Obj::{self, *}, 
-    err::{Err, ErrType::*}, 
+    err::{Err, ErrObj, ErrType::ErrCast}, 
     fun::{FnNative, FnBridge, Callable, FnMacro}, 
     env::Env
 };
 
 /// A trait for designating `Jester-rs` types
 pub trait TypeId: Clone {
+    #[allow(clippy::wrong_self_convention)]
     fn as_obj(self) -> Obj;
     fn type_str() -> &'static str;
     fn as_string(&self, env: &Env) -> String;
 }
 
+/// Counterpart of `TypeId`, converting an `Obj`
+/// back into a Rust type
+/// 
+/// ## Note
+/// Numbers convert like `is_num` and `is_int` do, so
+/// any number converts to `f64`, while integers that
+/// don't fit the target type fail to cast
+pub trait FromObj: Sized {
+    fn from_obj(obj: Obj) -> Err<Self>;
+}
+
+/// A Rust value that can be returned to `Jester-rs`
+pub trait IntoObj {
+    fn into_obj(self) -> Err<Obj>;
+}
+
 /// Marks `Jester-rs` type as primitive
 pub trait Primitive: Default + TypeId {}
 impl Primitive for i32 {}
@@ -102,6 +121,7 @@ impl TypeId for bool {
 
 impl TypeId for String {
     fn as_obj(self) -> Obj {
+        limit::alloc(self.len());
         Str(self)
     }
 
@@ -179,6 +199,20 @@ impl TypeId for FnBridge {
     }
 }
 
+impl TypeId for ErrObj {
+    fn as_obj(self) -> Obj {
+        Error(self)
+    }
+
+    fn type_str() -> &'static str {
+        "error"
+    }
+
+    fn as_string(&self, _: &Env) -> String {
+        format!("<{} error: {}>", self.kind(), self.message())
+    }
+}
+
 impl TypeId for Node {
     fn as_obj(self) -> Obj {
         Lst(self)
@@ -189,13 +223,41 @@ impl TypeId for Node {
     }
 
     fn as_string(&self, env: &Env) -> String {
+        format!("({})", self.items_string(env))
+    }
+}
+
+impl Node {
+    /// Type name of `Obj::Vct`, a `Node` evaluated as data
+    pub const VCT_TYPE: &'static str = "vector";
+
+    /// Returns the items of `self` as String, separated by spaces
+    pub fn items_string(&self, env: &Env) -> String {
         self
             .iter()
             .map(|obj| obj.as_ref().as_string(env))
             .reduce(|acc, next| format!("{} {}", acc, next))
+            .unwrap_or_default()
+    }
+}
+
+impl TypeId for Map {
+    fn as_obj(self) -> Obj {
+        Obj::Map(self)
+    }
+
+    fn type_str() -> &'static str {
+        "map"
+    }
+
+    fn as_string(&self, env: &Env) -> String {
+        self
+            .iter()
+            .map(|(key, val)| format!("{} {}", key.as_obj().as_string(env), val.as_string(env)))
+            .reduce(|acc, next| format!("{} {}", acc, next))
             .map_or(
-                "()".to_string(), 
-                |list| format!("({})", list))
+                "{}".to_string(), 
+                |map| format!("{{{}}}", map))
     }
 }
 
@@ -214,39 +276,21 @@ impl TypeId for () {
 }
 
 impl Obj {
-    /// Coerces `Obj` into  `T: Primitive`
-    /// 
-    /// ## Note
-    /// `self` must be `Primitive`
-    /// ```
-    /// Primitive: f64, i32, i64, i128, bool
-    /// ```
-    pub unsafe fn cast_as<T: Primitive>(&self) -> Err<T> {
-        match TypeId::as_obj(T::default()) {           
-            F64(x)  => Ok(std::mem::transmute_copy::<f64, T>(&x)),
-            I32(x)  => Ok(std::mem::transmute_copy::<i32, T>(&x)),
-            I64(x)  => Ok(std::mem::transmute_copy::<i64, T>(&x)),
-            I128(x) => Ok(std::mem::transmute_copy::<i128, T>(&x)),
-            Bool(x) => Ok(std::mem::transmute_copy::<bool, T>(&x)),
-            _ => Err(ErrCast)
-        }
-    }
-
     /// Coerces `Obj` into  `i32`
     /// 
     /// ## Note
     /// `self` must be `Primitive`
-    /// ```
+    /// ```text
     /// Primitive: f64, i32, i64, i128, bool
     /// ```
     pub fn as_i32(&self) -> Err<i32> {
         match *self {
             F64(x)  => Ok(x as i32),
-            I32(x)  => Ok(x as i32),
+            I32(x)  => Ok(x),
             I64(x)  => Ok(x as i32),
             I128(x) => Ok(x as i32),
             Bool(x) => Ok(x as i32),
-            _ => Err(ErrCast)
+            _ => Err(self.err_cast(i32::type_str()))
         }
     }
 
@@ -254,17 +298,17 @@ impl Obj {
     /// 
     /// ## Note
     /// `self` must be `Primitive`
-    /// ```
+    /// ```text
     /// Primitive: f64, i32, i64, i128, bool
     /// ```
     pub fn as_i64(&self) -> Err<i64> {
         match *self {
             F64(x)  => Ok(x as i64),
             I32(x)  => Ok(x as i64),
-            I64(x)  => Ok(x as i64),
+            I64(x)  => Ok(x),
             I128(x) => Ok(x as i64),
             Bool(x) => Ok(x as i64),
-            _ => Err(ErrCast)
+            _ => Err(self.err_cast(i64::type_str()))
         }
     }
 
@@ -272,7 +316,7 @@ impl Obj {
     /// 
     /// ## Note
     /// `self` must be `Primitive`
-    /// ```
+    /// ```text
     /// Primitive: f64, i32, i64, i128, bool
     /// ```
     pub fn as_i128(&self) -> Err<i128> {
@@ -280,9 +324,9 @@ impl Obj {
             F64(x)  => Ok(x as i128),
             I32(x)  => Ok(x as i128),
             I64(x)  => Ok(x as i128),
-            I128(x) => Ok(x as i128),
+            I128(x) => Ok(x),
             Bool(x) => Ok(x as i128),
-            _ => Err(ErrCast)
+            _ => Err(self.err_cast(i128::type_str()))
         }
     }
 
@@ -290,21 +334,21 @@ impl Obj {
     /// 
     /// ## Note
     /// `self` must be `Numeric`
-    /// ```
+    /// ```text
     /// Numeric: f64, i32, i64, i128
     /// ```
     pub fn as_f64(&self) -> Err<f64> {
         match *self {
-            F64(x)  => Ok(x as f64),
+            F64(x)  => Ok(x),
             I32(x)  => Ok(x as f64),
             I64(x)  => Ok(x as f64),
             I128(x) => Ok(x as f64),
-            _ => Err(ErrCast)
+            _ => Err(self.err_cast(f64::type_str()))
         }
     }
 
     /// Returns `Ok(Num)` if `self` is of type
-    /// ```
+    /// ```text
     /// f64, i32, i64, i128
     /// ```
     pub fn is_num(&self) -> Err<f64> {
@@ -312,197 +356,372 @@ impl Obj {
             I32(x)  => Ok(*x as f64),
             I64(x)  => Ok(*x as f64),
             I128(x) => Ok(*x as f64),
-            F64(x)  => Ok(*x as f64),
-            _ => Err(MisType)
+            F64(x)  => Ok(*x),
+            _ => Err(self.mis_type("number"))
         }
     } 
 
     /// Returns `Ok(Int)` if `self` is of type
-    /// ```
+    /// ```text
     /// i32, i64, i128
     /// ```
     pub fn is_int(&self) -> Err<i128> {
         match self {
             I32(x)  => Ok(*x as i128),
             I64(x)  => Ok(*x as i128),
-            I128(x) => Ok(*x as i128),
-            _ => Err(MisType)
+            I128(x) => Ok(*x),
+            _ => Err(self.mis_type("integer"))
         }
     } 
 
     /// Returns `Ok(&i32)` if `self` is of type
-    /// ```
+    /// ```text
     /// i32
     /// ```
     pub fn is_i32(&self) -> Err<&i32> {
         match self {
             I32(x) => Ok(x),
-            _ => Err(MisType)
+            _ => Err(self.mis_type(i32::type_str()))
         }
     }
 
     /// Returns `Ok(&mut i32)` if `self` is of type
-    /// ```
+    /// ```text
     /// i32
     /// ```
     pub fn is_i32_mut(&mut self) -> Err<&mut i32> {
         match self {
             I32(x) => Ok(x),
-            _ => Err(MisType)
+            _ => Err(self.mis_type(i32::type_str()))
         }
     }
 
     /// Returns `Ok(&i64)` if `self` is of type
-    /// ```
+    /// ```text
     /// i64
     /// ```
     pub fn is_i64(&self) -> Err<&i64> {
         match self {
             I64(x) => Ok(x),
-            _ => Err(MisType)
+            _ => Err(self.mis_type(i64::type_str()))
         }
     }
 
     /// Returns `Ok(&mut i64)` if `self` is of type
-    /// ```
+    /// ```text
     /// i64
     /// ```
     pub fn is_i64_mut(&mut self) -> Err<&mut i64> {
         match self {
             I64(x) => Ok(x),
-            _ => Err(MisType)
+            _ => Err(self.mis_type(i64::type_str()))
         }
     }
 
     /// Returns `Ok(&i128)` if `self` is of type
-    /// ```
+    /// ```text
     /// i128
     /// ```
     pub fn is_i128(&self) -> Err<&i128> {
         match self {
             I128(x) => Ok(x),
-            _ => Err(MisType)
+            _ => Err(self.mis_type(i128::type_str()))
         }
     }
 
     /// Returns `Ok(&mut i128)` if `self` is of type
-    /// ```
+    /// ```text
     /// i128
     /// ```
     pub fn is_i128_mut(&mut self) -> Err<&mut i128> {
         match self {
             I128(x) => Ok(x),
-            _ => Err(MisType)
+            _ => Err(self.mis_type(i128::type_str()))
         }
     }
 
     /// Returns `Ok(&f64)` if `self` is of type
-    /// ```
+    /// ```text
     /// f64
     /// ```
     pub fn is_f64(&self) -> Err<&f64> {
         match self {
             F64(x) => Ok(x),
-            _ => Err(MisType)
+            _ => Err(self.mis_type(f64::type_str()))
         }
     }
 
     /// Returns `Ok(&mut f64)` if `self` is of type
-    /// ```
+    /// ```text
     /// f64
     /// ```
     pub fn is_f64_mut(&mut self) -> Err<&mut f64> {
         match self {
             F64(x) => Ok(x),
-            _ => Err(MisType)
+            _ => Err(self.mis_type(f64::type_str()))
         }
     }
 
     /// Returns `Ok(&bool)` if `self` is of type
-    /// ```
+    /// ```text
     /// bool
     /// ```
     pub fn is_bool(&self) -> Err<&bool> {
         match self {
             Bool(x) => Ok(x),
-            _ => Err(MisType)
+            _ => Err(self.mis_type(bool::type_str()))
         }
     }
 
     /// Returns `Ok(&mut bool)` if `self` is of type
-    /// ```
+    /// ```text
     /// bool
     /// ```
     pub fn is_bool_mut(&mut self) -> Err<&mut bool> {
         match self {
             Bool(x) => Ok(x),
-            _ => Err(MisType)
+            _ => Err(self.mis_type(bool::type_str()))
         }
     }
 
     /// Returns `Ok(&String)` if `self` is of type
-    /// ```
+    /// ```text
     /// String
     /// ```
     pub fn is_string(&self) -> Err<&String> {
         match self {
             Str(x) => Ok(x),
-            _ => Err(MisType)
+            _ => Err(self.mis_type(String::type_str()))
         }
     }
 
     /// Returns `Ok(&mut String)` if `self` is of type
-    /// ```
+    /// ```text
     /// String
     /// ```
     pub fn is_string_mut(&mut self) -> Err<&mut String> {
         match self {
             Str(x) => Ok(x),
-            _ => Err(MisType)
+            _ => Err(self.mis_type(String::type_str()))
         }
     }
 
     /// Returns `Ok(&Node)` if `self` is of type
-    /// ```
-    /// List
+    /// ```text
+    /// List | Vector
     /// ```
     pub fn is_node(&self) -> Err<&Node> {
         match self {
-            Lst(x) => Ok(x),
-            _ => Err(MisType)
+            Lst(x) | Vct(x) => Ok(x),
+            _ => Err(self.mis_type(Node::type_str()))
         }
     }
 
     /// Returns `Ok(&mut Node)` if `self` is of type
-    /// ```
-    /// List
+    /// ```text
+    /// List | Vector
     /// ```
     pub fn is_node_mut(&mut self) -> Err<&mut Node> {
         match self {
-            Lst(x) => Ok(x),
-            _ => Err(MisType)
+            Lst(x) | Vct(x) => Ok(x),
+            _ => Err(self.mis_type(Node::type_str()))
         }
     }
 
-    /// Returns `Ok(&RcCell<Obj>)` if `self` is of type
+    /// Returns `Ok(&Map)` if `self` is of type
+    /// ```text
+    /// Map
     /// ```
+    pub fn is_map(&self) -> Err<&Map> {
+        match self {
+            Obj::Map(x) => Ok(x),
+            _ => Err(self.mis_type(Map::type_str()))
+        }
+    }
+
+    /// Returns `Ok(&mut Map)` if `self` is of type
+    /// ```text
+    /// Map
+    /// ```
+    pub fn is_map_mut(&mut self) -> Err<&mut Map> {
+        match self {
+            Obj::Map(x) => Ok(x),
+            _ => Err(self.mis_type(Map::type_str()))
+        }
+    }
+
+    /// Returns `Ok(&RcCell<Obj>)` if `self` is of type
+    /// ```text
     /// Symbol
     /// ```
     pub fn is_symbol(&self) -> Err<&RcCell<Obj>> {
         match self {
             Sym(x) => Ok(x),
-            _ => Err(MisType)
+            _ => Err(self.mis_type(RcCell::<Obj>::type_str()))
         }
     }
 
     /// Returns `Ok(&mut RcCell<Obj>)` if `self` is of type
-    /// ```
+    /// ```text
     /// Symbol
     /// ```
     pub fn is_symbol_mut(&mut self) -> Err<&mut RcCell<Obj>> {
         match self {
             Sym(x) => Ok(x),
-            _ => Err(MisType)
+            _ => Err(self.mis_type(RcCell::<Obj>::type_str()))
+        }
+    }
+
+    /// Returns `Ok(&ErrObj)` if `self` is of type
+    /// ```text
+    /// Error
+    /// ```
+    pub fn is_error(&self) -> Err<&ErrObj> {
+        match self {
+            Error(x) => Ok(x),
+            _ => Err(self.mis_type(ErrObj::type_str()))
+        }
+    }
+
+    /// Returns `Ok(&str)` if `self` is of type
+    /// ```text
+    /// String
+    /// ```
+    pub fn is_str(&self) -> Err<&str> {
+        self.is_string().map(String::as_str)
+    }
+
+    /// Returns `Ok(&FnNative)` if `self` is of type
+    /// ```text
+    /// Native
+    /// ```
+    pub fn is_native(&self) -> Err<&FnNative> {
+        match self {
+            Native(x) => Ok(x),
+            _ => Err(self.mis_type(FnNative::type_str()))
+        }
+    }
+
+    /// Returns `Ok(&FnBridge)` if `self` is of type
+    /// ```text
+    /// Bridge
+    /// ```
+    pub fn is_bridge(&self) -> Err<&FnBridge> {
+        match self {
+            Bridge(x) => Ok(x),
+            _ => Err(self.mis_type(FnBridge::type_str()))
+        }
+    }
+
+    /// Returns `Ok(&FnMacro)` if `self` is of type
+    /// ```text
+    /// Macro
+    /// ```
+    pub fn is_macro(&self) -> Err<&FnMacro> {
+        match self {
+            Macro(x) => Ok(x),
+            _ => Err(self.mis_type(FnMacro::type_str()))
+        }
+    }
+
+    /// Returns `Ok(())` if `self` is of type
+    /// ```text
+    /// Nil
+    /// ```
+    pub fn is_nil(&self) -> Err<()> {
+        match self {
+            Nil(_) => Ok(()),
+            _ => Err(self.mis_type(<()>::type_str()))
         }
     }
-}
\ No newline at end of file
+}
+
+impl<T: Into<Obj>> IntoObj for T {
+    fn into_obj(self) -> Err<Obj> {
+        Ok(self.into())
+    }
+}
+
+impl<T: IntoObj> IntoObj for Err<T> {
+    fn into_obj(self) -> Err<Obj> {
+        self?.into_obj()
+    }
+}
+
+impl FromObj for Obj {
+    fn from_obj(obj: Obj) -> Err<Self> {
+        Ok(obj)
+    }
+}
+
+impl FromObj for f64 {
+    fn from_obj(obj: Obj) -> Err<Self> {
+        obj.is_num()
+    }
+}
+
+/// Implements `FromObj` for integers, checking they fit
+macro_rules! from_int {
+    ($($int:ty),*) => {$(
+        impl FromObj for $int {
+            fn from_obj(obj: Obj) -> Err<Self> {
+                let int = obj.is_int()?;
+
+                <$int>::try_from(int).map_err(|_| ErrCast {
+                    expected: stringify!($int).to_string(),
+                    found: int.to_string()
+                })
+            }
+        }
+    )*};
+}
+
+from_int!(i32, i64, i128, u8, u16, u32, u64, usize);
+
+impl FromObj for bool {
+    fn from_obj(obj: Obj) -> Err<Self> {
+        obj.is_bool().copied()
+    }
+}
+
+impl FromObj for String {
+    fn from_obj(obj: Obj) -> Err<Self> {
+        match obj {
+            Str(x) => Ok(x),
+            _ => Err(obj.mis_type(String::type_str()))
+        }
+    }
+}
+
+impl FromObj for Node {
+    fn from_obj(obj: Obj) -> Err<Self> {
+        match obj {
+            Lst(x) | Vct(x) => Ok(x),
+            _ => Err(obj.mis_type(Node::type_str()))
+        }
+    }
+}
+
+impl FromObj for Map {
+    fn from_obj(obj: Obj) -> Err<Self> {
+        match obj {
+            Obj::Map(x) => Ok(x),
+            _ => Err(obj.mis_type(Map::type_str()))
+        }
+    }
+}
+
+impl FromObj for ErrObj {
+    fn from_obj(obj: Obj) -> Err<Self> {
+        match obj {
+            Error(x) => Ok(x),
+            _ => Err(obj.mis_type(ErrObj::type_str()))
+        }
+    }
+}
+
+impl FromObj for () {
+    fn from_obj(obj: Obj) -> Err<Self> {
+        obj.is_nil()
+    }
+}
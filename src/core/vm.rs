@@ -0,0 +1,986 @@
+use std::{
+    borrow::Cow,
+    fmt::Write
+};
+
+use super::{
+    obj::Obj::{self, *},
+    err::Err,
+    env::Env,
+    fun::{Callable, FnBridge, FnNative, Tail},
+    lex::Span,
+    node::{Node, NodeIter},
+    rc_cell::RcCell,
+    scope::Scope,
+    sync::Once,
+    type_id::TypeId,
+    gc::{Trace, Tracer}
+};
+
+/// Math bridges compiled to a single instruction
+#[derive(Clone, Copy)]
+pub enum Prim {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Eq,
+    Ne,
+    Le,
+    Ge,
+    Lt,
+    Gt
+}
+
+impl Prim {
+    /// Returns the primitive for a call to the bridge
+    /// `name` with `argc` args, if it has one
+    ///
+    /// ## Note
+    /// Arithmetic folds over any number of args,
+    /// comparisons are only compiled for exactly two
+    fn from_call(name: &str, argc: usize) -> Option<Self> {
+        let prim = match name {
+            "+"  => Prim::Add,
+            "-"  => Prim::Sub,
+            "*"  => Prim::Mul,
+            "/"  => Prim::Div,
+            "%"  => Prim::Mod,
+            "="  => Prim::Eq,
+            "!=" => Prim::Ne,
+            "<=" => Prim::Le,
+            ">=" => Prim::Ge,
+            "<"  => Prim::Lt,
+            ">"  => Prim::Gt,
+            _ => return None
+        };
+
+        let arity = match prim {
+            Prim::Add | Prim::Sub | Prim::Mul | Prim::Div | Prim::Mod => argc >= 1,
+            _ => argc == 2
+        };
+
+        arity.then_some(prim)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Prim::Add => "+",
+            Prim::Sub => "-",
+            Prim::Mul => "*",
+            Prim::Div => "/",
+            Prim::Mod => "%",
+            Prim::Eq  => "=",
+            Prim::Ne  => "!=",
+            Prim::Le  => "<=",
+            Prim::Ge  => ">=",
+            Prim::Lt  => "<",
+            Prim::Gt  => ">"
+        }
+    }
+
+    /// Applies the primitive to the evaluated `args`,
+    /// matching its bridge in the math lib
+    fn apply(self, args: &mut [Obj]) -> Err<Obj> {
+        let (first, rest) = args.split_first_mut().unwrap();
+        let mut first = std::mem::replace(first, Nil(()));
+
+        let cmp = match self {
+            Prim::Eq => first.eq(&rest[0])?,
+            Prim::Ne => !first.eq(&rest[0])?,
+            Prim::Le => first.le_eq(&rest[0])?,
+            Prim::Ge => !first.le(&rest[0])?,
+            Prim::Lt => first.le(&rest[0])?,
+            Prim::Gt => !first.le_eq(&rest[0])?,
+
+            Prim::Sub if rest.is_empty() => {
+                first.neg()?;
+                return Ok(first)
+            }
+
+            _ => {
+                for obj in rest {
+                    let obj = std::mem::replace(obj, Nil(()));
+
+                    match self {
+                        Prim::Add => first.add(obj)?,
+                        Prim::Sub => first.sub(obj)?,
+                        Prim::Mul => first.mul(obj)?,
+                        Prim::Div => first.div(obj)?,
+                        _ => first.modulos(obj)?
+                    }
+                }
+
+                return Ok(first)
+            }
+        };
+
+        Ok(cmp.as_obj())
+    }
+}
+
+/// A `Chunk` instruction
+///
+/// ## Note
+/// Jump targets are indices into the `Chunk`. Forms the
+/// compiler doesn't know are kept whole and handed back
+/// to `Env::eval`
+pub enum Op {
+    /// Pushes a constant
+    Const(Obj),
+    /// Pushes the `i`th param of the function's frame
+    Local(usize),
+    /// Pushes the value bound to a symbol
+    Load(RcCell<Obj>),
+    /// Pushes the global value of a symbol
+    Global(RcCell<Obj>),
+    /// Assigns the top of the stack to the `i`th param
+    SetLocal(usize),
+    /// Assigns the top of the stack to a symbol
+    Store(RcCell<Obj>),
+    /// Pops the top of the stack
+    Pop,
+    /// Jumps to a target
+    Jump(usize),
+    /// Pops a `bool`, jumping to a target if false
+    JumpIfNot(usize),
+    /// Pops a `bool`, jumping to a target if true
+    JumpIf(usize),
+    /// Jumps to a target unless a global symbol still holds
+    /// the bridge or macro it held when compiled
+    Guard(RcCell<Obj>, Obj, usize),
+    /// Keeps the top of the stack if it is a `FnNative`,
+    /// or else pops it and jumps to a target
+    Callable(usize),
+    /// Pops args and a callee, pushing the result of the call
+    Call(usize, Obj),
+    /// Pops args and a callee, replacing the running
+    /// function with the callee
+    TailCall(usize, Obj),
+    /// Pops args, pushing the result of a primitive
+    Prim(Prim, usize),
+    /// Enters a new frame on top of the current scope
+    Enter,
+    /// Pops a value, binding it to a symbol in the current frame
+    Bind(RcCell<Obj>),
+    /// Marks the frame made by the last `Enter` as bound
+    Seal,
+    /// Leaves the frame made by the last `Enter`
+    Leave,
+    /// Pushes the result of evaluating a form
+    Eval(Obj),
+    /// Returns a form to evaluate in place of the function
+    TailEval(Obj),
+    /// Returns the top of the stack
+    Ret
+}
+
+impl Op {
+    /// Returns the instruction for display
+    pub fn display(&self, env: &Env) -> String {
+        let sym = |cell: &RcCell<Obj>| env
+            .get_sym_id(cell)
+            .unwrap_or_else(|| "?".to_string());
+
+        match self {
+            Op::Const(obj)        => format!("const {}", obj.display(env)),
+            Op::Local(i)          => format!("local {}", i),
+            Op::Load(cell)        => format!("load {}", sym(cell)),
+            Op::Global(cell)      => format!("global {}", sym(cell)),
+            Op::SetLocal(i)       => format!("set-local {}", i),
+            Op::Store(cell)       => format!("store {}", sym(cell)),
+            Op::Pop               => "pop".to_string(),
+            Op::Jump(to)          => format!("jump {:04}", to),
+            Op::JumpIfNot(to)     => format!("jump-if-not {:04}", to),
+            Op::JumpIf(to)        => format!("jump-if {:04}", to),
+            Op::Guard(cell, _, to) => format!("guard {} else {:04}", sym(cell), to),
+            Op::Callable(to)      => format!("callable else {:04}", to),
+            Op::Call(argc, _)     => format!("call {}", argc),
+            Op::TailCall(argc, _) => format!("tail-call {}", argc),
+            Op::Prim(prim, argc)  => format!("prim {} {}", prim.name(), argc),
+            Op::Enter             => "enter".to_string(),
+            Op::Bind(cell)        => format!("bind {}", sym(cell)),
+            Op::Seal              => "seal".to_string(),
+            Op::Leave             => "leave".to_string(),
+            Op::Eval(form)        => format!("eval {}", form.as_string(env)),
+            Op::TailEval(form)    => format!("tail-eval {}", form.as_string(env)),
+            Op::Ret               => "ret".to_string()
+        }
+    }
+}
+
+/// The bytecode of a function body, run by a stack machine
+///
+/// ## Note
+/// - params are read straight from the function's frame, as
+///   are symbols no enclosing scope binds from their global
+///   cell, other symbols are looked up through the current scope
+/// - `if`, `when`, `unless`, `do`, `let`, `loop`, `set`,
+///   `quote`, `apply` and the math bridges are compiled
+///   in place, as are expansions of pure macros, each 
+///   guarded by a check that its symbol wasn't rebound.
+///   Other macros are expanded each time their call runs
+/// - calls in tail position replace the running function,
+///   so tail recursion runs in constant Rust stack
+///
+/// ## Example
+/// ```text
+/// (defun fact (n acc)
+///     (if (= n 0)
+///         acc
+///         (fact (- n 1) (* n acc))))
+///
+/// (disassemble fact)
+/// ```
+/// compiles to...
+/// ```text
+/// 0000 guard IF else 0028
+/// 0001 guard = else 0005
+/// 0002 local 0
+/// 0003 const 0
+/// 0004 prim = 2
+/// ...
+/// 0010 global FACT
+/// 0011 callable else 0026
+/// ...
+/// 0024 tail-call 2
+/// ```
+#[derive(Default)]
+pub struct Chunk {
+    ops: Vec<Op>,
+    spans: Vec<Option<Span>>
+}
+
+impl Trace for Op {
+    fn trace(&self, tracer: &mut Tracer) {
+        match self {
+            Op::Load(cell) | Op::Global(cell) | Op::Store(cell) | Op::Bind(cell) => {
+                tracer.cell(cell)
+            }
+            Op::Guard(cell, obj, _) => {
+                tracer.cell(cell);
+                obj.trace(tracer);
+            }
+            Op::Const(obj) | Op::Call(_, obj) | Op::TailCall(_, obj) | Op::Eval(obj) | Op::TailEval(obj) => {
+                obj.trace(tracer)
+            }
+            _ => ()
+        }
+    }
+}
+
+impl Trace for Chunk {
+    fn trace(&self, tracer: &mut Tracer) {
+        self.ops
+            .iter()
+            .for_each(|op| op.trace(tracer));
+    }
+}
+
+impl Trace for Once<Chunk> {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Some(chunk) = self.get() {
+            chunk.trace(tracer);
+        }
+    }
+}
+
+impl Chunk {
+    /// Compiles the body of a function taking `params`,
+    /// defined in `scope`
+    pub fn compile(env: &Env, params: &Node, body: &Node, scope: &Option<RcCell<Scope>>) -> Self {
+        let mut unique: Vec<RcCell<Obj>> = Vec::new();
+
+        // frames bind a repeated param once, in its first slot
+        for param in params.iter() {
+            if let Sym(sym) = &*param.as_ref() {
+                if !unique.iter().any(|rhs| rhs.raw_eq(sym)) {
+                    unique.push(sym.clone());
+                }
+            }
+        }
+
+        let mut compiler = Compiler {
+            env,
+            params: unique,
+            lets: Vec::new(),
+            closure: scope.clone(),
+            binding: scope
+                .as_ref()
+                .is_some_and(|scope| scope.as_ref().is_binding()),
+            span: body.span().cloned(),
+            chunk: Chunk::default()
+        };
+
+        compiler.progn(body.iter(), true);
+        compiler.emit(Op::Ret);
+        compiler.chunk
+    }
+
+    pub fn ops(&self) -> &[Op] {
+        &self.ops
+    }
+
+    /// Returns a listing of the instructions
+    pub fn disassemble(&self, env: &Env) -> String {
+        let mut out = String::new();
+
+        for (i, op) in self.ops.iter().enumerate() {
+            let _ = writeln!(out, "{:04} {}", i, op.display(env));
+        }
+
+        out
+    }
+}
+
+struct Compiler<'a> {
+    env: &'a Env,
+    // params in frame order
+    params: Vec<RcCell<Obj>>,
+    // symbols bound by enclosing `let`s
+    lets: Vec<RcCell<Obj>>,
+    // scope the function was defined in
+    closure: Option<RcCell<Scope>>,
+    // whether `closure` may still bind new symbols
+    binding: bool,
+    // span of the innermost form with one
+    span: Option<Span>,
+    chunk: Chunk
+}
+
+impl<'a> Compiler<'a> {
+    fn emit(&mut self, op: Op) -> usize {
+        self.chunk.ops.push(op);
+        self.chunk.spans.push(self.span.clone());
+        self.chunk.ops.len() - 1
+    }
+
+    /// Points the jump at `at` to the next instruction
+    fn patch(&mut self, at: usize) {
+        let next = self.chunk.ops.len();
+
+        match &mut self.chunk.ops[at] {
+            Op::Jump(to)
+            | Op::JumpIfNot(to)
+            | Op::JumpIf(to)
+            | Op::Guard(_, _, to)
+            | Op::Callable(to) => *to = next,
+            _ => unreachable!("patched a non-jump")
+        }
+    }
+
+    /// Returns the slot of `sym` if it is a param
+    /// not shadowed by a `let`
+    fn local(&self, sym: &RcCell<Obj>) -> Option<usize> {
+        if self.lets.iter().any(|rhs| rhs.raw_eq(sym)) {
+            return None
+        }
+
+        self.params
+            .iter()
+            .position(|rhs| rhs.raw_eq(sym))
+    }
+
+    /// Returns whether no scope the body runs in binds `sym`
+    /// 
+    /// ## Note
+    /// Frames are bound as they are made, so a symbol the 
+    /// closure doesn't bind when compiled never will, unless 
+    /// the closure was made while a `let` was binding its frame
+    fn is_global(&self, sym: &RcCell<Obj>) -> bool {
+        let bound = self.lets
+            .iter()
+            .chain(self.params.iter())
+            .any(|rhs| rhs.raw_eq(sym));
+
+        let captured = self.closure
+            .as_ref()
+            .is_some_and(|scope| self.binding || scope.as_ref().lookup(sym).is_some());
+
+        !bound && !captured
+    }
+
+    fn load(&mut self, sym: &RcCell<Obj>) {
+        if let Some(i) = self.local(sym) {
+            self.emit(Op::Local(i));
+        }
+        else if self.is_global(sym) {
+            self.emit(Op::Global(sym.clone()));
+        }
+        else {
+            self.emit(Op::Load(sym.clone()));
+        }
+    }
+
+    fn compile(&mut self, obj: &Obj, tail: bool) {
+        match obj {
+            Sym(sym) => self.load(sym),
+
+            Lst(node) if !node.is_empty() => {
+                let span = node
+                    .span()
+                    .cloned()
+                    .or_else(|| self.span.clone());
+
+                let prev = std::mem::replace(&mut self.span, span);
+                self.list(obj, node, tail);
+                self.span = prev;
+            }
+
+            _ => {
+                self.emit(Op::Const(obj.clone()));
+            }
+        }
+    }
+
+    /// Compiles each form of `body`, keeping the last value
+    fn progn(&mut self, body: NodeIter, tail: bool) {
+        let last = body.len().saturating_sub(1);
+
+        for (i, obj) in body.enumerate() {
+            self.compile(&obj.as_ref(), tail && i == last);
+
+            if i != last {
+                self.emit(Op::Pop);
+            }
+        }
+    }
+
+    fn fallback(&mut self, form: &Obj, tail: bool) {
+        match tail {
+            true  => self.emit(Op::TailEval(form.clone())),
+            false => self.emit(Op::Eval(form.clone()))
+        };
+    }
+
+    /// Compiles `body`, falling back to evaluating `form`
+    /// if `head` no longer holds `expect`
+    fn guarded<F>(&mut self, head: &RcCell<Obj>, expect: Obj, form: &Obj, tail: bool, body: F)
+    where
+        F: FnOnce(&mut Self)
+    {
+        let guard = self.emit(Op::Guard(head.clone(), expect, 0));
+        body(self);
+
+        let end = self.emit(Op::Jump(0));
+        self.patch(guard);
+        self.fallback(form, tail);
+        self.patch(end);
+    }
+
+    fn list(&mut self, form: &Obj, node: &Node, tail: bool) {
+        let head = match node.get(0).as_deref() {
+            Ok(Sym(head)) => head.clone(),
+            _ => return self.fallback(form, tail)
+        };
+
+        let head = &head;
+
+        // only global bridges and macros are compiled in place, 
+        // copied out as expanding a macro may set its symbol
+        if self.is_global(head) {
+            match head.clone_inner() {
+                Bridge(f) if !f.is_denied() && self.special(head, &f, form, node, tail) => return,
+
+                // a pure macro failing to expand is left to 
+                // report its error once its call runs
+                Macro(f) if f.is_pure() => {
+                    if let Ok(exp) = f.expand(self.env, node.iter_from(1)) {
+                        let expect = Macro(f.clone());
+                        return self.guarded(head, expect, form, tail, |c| c.compile(&exp, tail))
+                    }
+                }
+
+                _ => ()
+            }
+        }
+
+        self.load(head);
+        let callee = self.emit(Op::Callable(0));
+
+        for arg in node.iter_from(1) {
+            self.compile(&arg.as_ref(), false);
+        }
+
+        let argc = node.len() - 1;
+
+        match tail {
+            true  => self.emit(Op::TailCall(argc, form.clone())),
+            false => self.emit(Op::Call(argc, form.clone()))
+        };
+
+        let end = self.emit(Op::Jump(0));
+        self.patch(callee);
+        self.fallback(form, tail);
+        self.patch(end);
+    }
+
+    /// Compiles a call to a bridge the compiler knows,
+    /// returning false if `f` is unknown or the call is
+    /// malformed, leaving the bridge to report it
+    fn special(&mut self, head: &RcCell<Obj>, f: &FnBridge, form: &Obj, node: &Node, tail: bool) -> bool {
+        let args = node.iter_from(1);
+        let expect = Bridge(f.clone());
+
+        match (f.name().as_str(), args.len()) {
+            ("quote", 1..) => {
+                let item = args[0].clone_inner();
+                self.guarded(head, expect, form, tail, |c| { c.emit(Op::Const(item)); });
+            }
+
+            ("if", 2..) => self.guarded(head, expect, form, tail, |c| {
+                c.compile(&args[0].as_ref(), false);
+                let jump = c.emit(Op::JumpIfNot(0));
+
+                c.compile(&args[1].as_ref(), tail);
+                let end = c.emit(Op::Jump(0));
+
+                c.patch(jump);
+
+                match args.get(2).as_deref() {
+                    Ok(not) => c.compile(not, tail),
+                    Err(_) => { c.emit(Op::Const(Nil(()))); }
+                }
+
+                c.patch(end);
+            }),
+
+            (name @ ("when" | "unless"), 2..) => self.guarded(head, expect, form, tail, |c| {
+                c.compile(&args[0].as_ref(), false);
+
+                let jump = match name {
+                    "when" => c.emit(Op::JumpIfNot(0)),
+                    _ => c.emit(Op::JumpIf(0))
+                };
+
+                c.progn(args.shift(), tail);
+                let end = c.emit(Op::Jump(0));
+
+                c.patch(jump);
+                c.emit(Op::Const(Nil(())));
+                c.patch(end);
+            }),
+
+            ("do", 1..) => self.guarded(head, expect, form, tail, |c| c.progn(args, tail)),
+
+            ("set", 2..) => {
+                let sym = match args.get(0).as_deref() {
+                    Ok(Sym(sym)) => sym.clone(),
+                    _ => return false
+                };
+
+                self.guarded(head, expect, form, tail, |c| {
+                    c.compile(&args[1].as_ref(), false);
+
+                    match c.local(&sym) {
+                        Some(i) => c.emit(Op::SetLocal(i)),
+                        None => c.emit(Op::Store(sym))
+                    };
+                })
+            }
+
+            ("loop", 2..) => self.guarded(head, expect, form, tail, |c| {
+                c.emit(Op::Const(Nil(())));
+
+                let top = c.chunk.ops.len();
+                c.compile(&args[0].as_ref(), false);
+                let exit = c.emit(Op::JumpIfNot(0));
+
+                // drop the value of the last iteration
+                c.emit(Op::Pop);
+                c.progn(args.shift(), false);
+                c.emit(Op::Jump(top));
+
+                c.patch(exit);
+            }),
+
+            ("let", 2..) => {
+                let vars = match args.get(0).as_deref() {
+                    Ok(Lst(vars)) if vars.len() % 2 == 0 => vars.clone(),
+                    _ => return false
+                };
+
+                let params: Option<Vec<RcCell<Obj>>> = vars
+                    .iter()
+                    .step_by(2)
+                    .map(|param| match &*param.as_ref() {
+                        Sym(sym) => Some(sym.clone()),
+                        _ => None
+                    })
+                    .collect();
+
+                let params = match params {
+                    Some(params) => params,
+                    None => return false
+                };
+
+                self.guarded(head, expect, form, tail, |c| {
+                    let depth = c.lets.len();
+                    c.emit(Op::Enter);
+
+                    // each input sees the params bound before it
+                    for (param, input) in params.into_iter().zip(vars.iter().skip(1).step_by(2)) {
+                        c.compile(&input.as_ref(), false);
+                        c.emit(Op::Bind(param.clone()));
+                        c.lets.push(param);
+                    }
+
+                    c.emit(Op::Seal);
+                    c.progn(args.shift(), tail);
+                    c.emit(Op::Leave);
+                    c.lets.truncate(depth);
+                })
+            }
+
+            // (apply f '(..args)) with only list args is the call (f ..args)
+            ("apply", 1..) => {
+                if args.skip(1).any(|item| matches!(*item.as_ref(), Sym(_))) {
+                    return false
+                }
+
+                let mut call = Node::from(vec![args[0].clone()]);
+
+                for item in args.skip(1) {
+                    match &*item.as_ref() {
+                        Lst(lst) | Vct(lst) => lst.iter().for_each(|elem| call.push(elem.clone())),
+                        _ => call.push(item.clone())
+                    }
+                }
+
+                if let Some(span) = node.span() {
+                    call.set_span(span.clone());
+                }
+
+                let call = call.as_obj();
+                self.guarded(head, expect, form, tail, |c| c.compile(&call, tail));
+            }
+
+            (name, argc) => {
+                let prim = match Prim::from_call(name, argc) {
+                    Some(prim) => prim,
+                    None => return false
+                };
+
+                self.guarded(head, expect, form, tail, |c| {
+                    for arg in args {
+                        c.compile(&arg.as_ref(), false);
+                    }
+
+                    c.emit(Op::Prim(prim, argc));
+                })
+            }
+        }
+
+        true
+    }
+}
+
+/// How a `Chunk` finished running
+enum Exit {
+    Ret(Obj),
+    Eval(Obj),
+    Call(FnNative, Scope, Obj)
+}
+
+struct Vm<'a> {
+    env: &'a Env,
+    chunk: &'a Chunk,
+    // frame of the running function
+    frame: RcCell<Scope>,
+    stack: Vec<Obj>,
+    // scopes to restore on `Leave`
+    scopes: Vec<Option<RcCell<Scope>>>,
+    pc: usize
+}
+
+/// Runs the compiled body of `f` in the current scope of
+/// `env`, which must be a frame from `FnNative::bind`
+///
+/// ## Note
+/// Tail calls to other functions run in the same loop,
+/// replacing the innermost call on the call stack
+pub fn run(env: &Env, f: &FnNative) -> Err<Tail> {
+    let mut f = Cow::Borrowed(f);
+
+    loop {
+        let vm = Vm {
+            env,
+            chunk: f.chunk(env),
+            frame: env.scope().unwrap(),
+            stack: Vec::new(),
+            scopes: Vec::new(),
+            pc: 0
+        };
+
+        match vm.run()? {
+            Exit::Ret(obj) => return Ok(Tail::Ret(obj)),
+            Exit::Eval(form) => return Ok(Tail::Eval(form)),
+            Exit::Call(next, scope, form) => {
+                env.push_call(next.name(), &form, true);
+                env.set_scope(Some(RcCell::from(scope)));
+                f = Cow::Owned(next);
+            }
+        }
+    }
+}
+
+/// Returns whether `obj` is the bridge or macro `expect`
+fn same(obj: &Obj, expect: &Obj) -> bool {
+    match (obj, expect) {
+        (Bridge(lhs), Bridge(rhs)) => lhs.same_def(rhs),
+        (Macro(lhs), Macro(rhs)) => lhs.same_def(rhs),
+        _ => false
+    }
+}
+
+impl<'a> Vm<'a> {
+    fn run(mut self) -> Err<Exit> {
+        loop {
+            match self.step() {
+                Ok(None) => (),
+                Ok(Some(exit)) => return Ok(exit),
+                Err(err) => {
+                    return Err(match &self.chunk.spans[self.pc - 1] {
+                        Some(span) => err.at(span),
+                        None => err
+                    })
+                }
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Obj {
+        self.stack
+            .pop()
+            .unwrap()
+    }
+
+    fn top(&self) -> Obj {
+        self.stack
+            .last()
+            .unwrap()
+            .clone()
+    }
+
+    /// Pops `argc` args and their callee
+    fn callee(&mut self, argc: usize) -> (FnNative, Vec<Obj>) {
+        let args = self.stack.split_off(self.stack.len() - argc);
+
+        match self.pop() {
+            Native(f) => (f, args),
+            _ => unreachable!("callee is always native")
+        }
+    }
+
+    fn step(&mut self) -> Err<Option<Exit>> {
+        let chunk = self.chunk;
+        let op = &chunk.ops[self.pc];
+
+        self.pc += 1;
+        self.env.step()?;
+
+        match op {
+            Op::Const(obj) => self.stack.push(obj.clone()),
+
+            Op::Local(i) => {
+                let obj = self.frame.as_ref().slot(*i).clone_inner();
+                self.stack.push(obj);
+            }
+
+            Op::Load(sym) => {
+                let obj = self.env.lookup(sym).clone_inner();
+                self.stack.push(obj);
+            }
+
+            Op::Global(sym) => self.stack.push(sym.clone_inner()),
+
+            Op::SetLocal(i) => {
+                self.frame.as_ref().slot(*i).replace(self.top())?;
+            }
+
+            Op::Store(sym) => {
+                self.env.lookup(sym).replace(self.top())?;
+            }
+
+            Op::Pop => {
+                self.pop();
+            }
+
+            Op::Jump(to) => self.pc = *to,
+
+            Op::JumpIfNot(to) => {
+                if !*self.pop().is_bool()? {
+                    self.pc = *to;
+                }
+            }
+
+            Op::JumpIf(to) => {
+                if *self.pop().is_bool()? {
+                    self.pc = *to;
+                }
+            }
+
+            Op::Guard(head, expect, to) => {
+                if !same(&head.as_ref(), expect) {
+                    self.pc = *to;
+                }
+            }
+
+            Op::Callable(to) => {
+                if !matches!(self.stack.last(), Some(Native(_))) {
+                    self.pop();
+                    self.pc = *to;
+                }
+            }
+
+            Op::Call(argc, form) => {
+                let (f, args) = self.callee(*argc);
+                let scope = f.bind_values(args)?;
+                let obj = self.env.apply_native(&f, form, scope)?;
+
+                self.stack.push(obj);
+            }
+
+            Op::TailCall(argc, form) => {
+                let (f, args) = self.callee(*argc);
+                let scope = f.bind_values(args)?;
+
+                return Ok(Some(Exit::Call(f, scope, form.clone())))
+            }
+
+            Op::Prim(prim, argc) => {
+                let base = self.stack.len() - argc;
+                let obj = prim.apply(&mut self.stack[base..])?;
+
+                self.stack.truncate(base);
+                self.stack.push(obj);
+            }
+
+            Op::Enter => {
+                let scope = RcCell::from(Scope::binding(self.env.scope()));
+                self.scopes.push(self.env.set_scope(Some(scope)));
+            }
+
+            Op::Bind(sym) => {
+                let val = self.pop();
+
+                self.env
+                    .scope()
+                    .unwrap()
+                    .as_mut()?
+                    .bind(sym, val)?;
+            }
+
+            Op::Seal => {
+                self.env
+                    .scope()
+                    .unwrap()
+                    .as_mut()?
+                    .seal();
+            }
+
+            Op::Leave => {
+                let scope = self.scopes.pop().unwrap();
+                self.env.set_scope(scope);
+            }
+
+            Op::Eval(form) => {
+                let obj = self.env.eval(form)?;
+                self.stack.push(obj);
+            }
+
+            Op::TailEval(form) => return Ok(Some(Exit::Eval(form.clone()))),
+
+            Op::Ret => return Ok(Some(Exit::Ret(self.pop())))
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{
+        builder::EnvBuilder,
+        env::Env
+    };
+
+    /// Definitions the forms of `compiled_matches_interpreted` call
+    const SETUP: &str = "
+        (defun fact (n acc) (if (= n 0) acc (fact (- n 1) (* n acc))))
+        (defun fib (n) (if (< n 2) n (+ (fib (- n 1)) (fib (- n 2)))))
+        (defun sum (n) (let (s 0) (for i in 0 to n (set s (+ s i))) s))
+        (defun count (n) (let (i 0) (loop (< i n) (incr i 1)) i))
+        (defun adder (a) (lambda (b) (+ a b)))
+        (defun shadow (n) (let (n (* n 2)) (set n (+ n 1)) n))
+        (defun small (x) (unless (> x 3) (+ x 100)))
+        (defun quoted () '(1 2 3))
+        (defun spread (f xs) (apply f xs))
+        (defun bad (x) (+ x \"s\"))
+    ";
+
+    /// Evaluates `src` after `SETUP`, returning its value
+    /// as printed or the kind of error it failed with
+    fn eval(interpreted: bool, src: &str) -> String {
+        let env = EnvBuilder::new()
+            .interpreted(interpreted)
+            .build()
+            .unwrap();
+
+        env.eval_str(SETUP).unwrap();
+
+        match env.eval_str(src) {
+            Ok(obj) => obj.display(&env),
+            Err(err) => format!("error: {}", err.kind())
+        }
+    }
+
+    #[test]
+    fn compiled_matches_interpreted() {
+        let forms = [
+            "(fact 10 1)",
+            "(fib 15)",
+            "(sum 100)",
+            "(count 50)",
+            "((adder 5) 10)",
+            "(shadow 5)",
+            "(small 1)",
+            "(small 5)",
+            "(quoted)",
+            "(spread + '(1 2 3))",
+            "(bad 1)",
+            "(fact 2147483647 2)",
+            "(sum 20000)",
+            // macros expand each time their call runs
+            "(set n 0) (defmacro m () (set n (+ n 1)) 1) (defun f (x) (if x (m) 2)) (f False) (f False) n",
+            "(set k 1) (defmacro m () k) (defun f () (m)) (f) (set k 2) (f)",
+            "(defmacro m (x) (throw \"bad\")) (defun f () (m 1)) (f)",
+            // closures made while a `let` binds its frame
+            "(let (f (lambda () y) z (f) y 5) (list z (f)))",
+            "(defun g () (let (f (lambda () y) z (f) y 5) (list z (f)))) (g)",
+            "(for i in 0 to 10 (incr i 1))"
+        ];
+
+        for src in forms {
+            assert_eq!(eval(false, src), eval(true, src), "{}", src);
+        }
+    }
+
+    #[test]
+    fn closure_cycles_are_collected() {
+        let env = Env::new().unwrap();
+
+        env.eval_str("(defun make (n) (let (f (lambda () n)) f))").unwrap();
+        env.collect();
+        let before = env.live_objects();
+
+        // each closure is bound in the frame it captured
+        env.eval_str("(for i in 0 to 200 (make i))").unwrap();
+        env.collect();
+        let after = env.live_objects();
+
+        assert_eq!(after.cells, before.cells);
+        assert_eq!(after.scopes, before.scopes);
+    }
+}
@@ -0,0 +1,688 @@
+//! Line editing for the REPL, on a terminal in raw mode
+//!
+//! ## Note
+//! Raw mode is set with `stty`, so no bindings to the C
+//! library are needed. Input that isn't a terminal is read
+//! a line at a time, without editing
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, BufRead, IsTerminal, Read, Write},
+    path::PathBuf,
+    process::{Command, Stdio}
+};
+
+/// Most entries kept in the history
+const HISTORY_LEN: usize = 1000;
+
+/// Characters ending the symbol being completed
+const BREAKS: &str = " \t\n()[]{}'\",;";
+
+/// Terminal width assumed if `stty` can't report it
+const WIDTH: usize = 80;
+
+/// Returns how many brackets `src` leaves open, negative if it
+/// closes more than it opens, skipping strings and comments
+pub fn depth(src: &str) -> isize {
+    let mut depth = 0;
+    let mut com = false;
+    let mut str = false;
+
+    for ch in src.chars() {
+        match ch {
+            '\n' if !str => com = false,
+            ';' if !str => com = !com,
+            '"' if !com => str = !str,
+            '(' | '[' | '{' if !str && !com => depth += 1,
+            ')' | ']' | '}' if !str && !com => depth -= 1,
+            _ => ()
+        }
+    }
+
+    depth
+}
+
+/// Returns `src` on a single line, without comments, folding
+/// whitespace outside strings into single spaces
+///
+/// ## Note
+/// Strings are kept as typed, so an entry holds the newlines
+/// of multi-line strings, escaped by `escape` in the file
+fn flatten(src: &str) -> String {
+    let mut flat = String::new();
+    let mut com = false;
+    let mut str = false;
+
+    for ch in src.chars() {
+        match ch {
+            '\n' if !str => com = false,
+            ';' if !str => {
+                com = !com;
+                continue
+            }
+            '"' if !com => str = !str,
+            _ => ()
+        }
+
+        match ch {
+            _ if com => (),
+            _ if str || ch == '"' => flat.push(ch),
+            _ if ch.is_whitespace() => {
+                if !flat.is_empty() && !flat.ends_with(' ') {
+                    flat.push(' ');
+                }
+            }
+            _ => flat.push(ch)
+        }
+    }
+
+    flat.trim_end().to_string()
+}
+
+/// Escapes `entry` for a line of the history file
+fn escape(entry: &str) -> String {
+    entry
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Reads an entry escaped by `escape`
+fn unescape(line: &str) -> String {
+    let mut entry = String::new();
+    let mut chars = line.chars();
+
+    while let Some(ch) = chars.next() {
+        let ch = match (ch, chars.clone().next()) {
+            ('\\', Some(next @ ('\\' | 'n' | 'r'))) => {
+                chars.next();
+
+                match next {
+                    'n' => '\n',
+                    'r' => '\r',
+                    _   => '\\'
+                }
+            }
+            _ => ch
+        };
+
+        entry.push(ch);
+    }
+
+    entry
+}
+
+/// Runs `stty` on the terminal of stdin, returning what it printed
+fn stty(args: &[&str]) -> io::Result<String> {
+    let out = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()?;
+
+    match out.status.success() {
+        true => Ok(String::from_utf8_lossy(&out.stdout).trim().to_string()),
+        false => Err(io::Error::other("stty failed"))
+    }
+}
+
+/// Keeps the terminal in raw mode, restoring it once dropped
+struct RawMode {
+    saved: String
+}
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        let saved = stty(&["-g"])?;
+        stty(&["-icanon", "-echo", "-isig", "-ixon", "-icrnl", "-iexten", "min", "1", "time", "0"])?;
+
+        Ok(Self {
+            saved
+        })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+/// A key read from the terminal
+#[derive(Clone, Copy, PartialEq)]
+enum Key {
+    Char(char),
+    /// a letter held with Ctrl
+    Ctrl(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    /// a sequence that isn't bound
+    Other
+}
+
+/// Reads a key, or `None` at the end of input
+fn read_key(input: &mut impl Read) -> io::Result<Option<Key>> {
+    let byte = match read_byte(input)? {
+        Some(byte) => byte,
+        None => return Ok(None)
+    };
+
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x7f | 0x08 => Key::Backspace,
+        0x1b => read_escape(input)?,
+        0x01..=0x1a => Key::Ctrl((b'a' + byte - 1) as char),
+        0x00..=0x1f => Key::Other,
+        _ => read_char(input, byte)?
+    };
+
+    Ok(Some(key))
+}
+
+fn read_byte(input: &mut impl Read) -> io::Result<Option<u8>> {
+    let mut buf = [0];
+
+    match input.read(&mut buf)? {
+        0 => Ok(None),
+        _ => Ok(Some(buf[0]))
+    }
+}
+
+/// Reads the rest of the UTF-8 character beginning with `first`
+fn read_char(input: &mut impl Read, first: u8) -> io::Result<Key> {
+    let len = match first {
+        0xf0.. => 4,
+        0xe0.. => 3,
+        0xc0.. => 2,
+        _ => 1
+    };
+
+    let mut buf = vec![first];
+
+    for _ in 1..len {
+        match read_byte(input)? {
+            Some(byte) => buf.push(byte),
+            None => break
+        }
+    }
+
+    Ok(std::str::from_utf8(&buf)
+        .ok()
+        .and_then(|ch| ch.chars().next())
+        .map_or(Key::Other, Key::Char))
+}
+
+/// Reads the escape sequence following `ESC`
+fn read_escape(input: &mut impl Read) -> io::Result<Key> {
+    match read_byte(input)? {
+        Some(b'[') | Some(b'O') => (),
+        _ => return Ok(Key::Other)
+    }
+
+    let mut num = 0;
+
+    loop {
+        let key = match read_byte(input)? {
+            Some(digit @ b'0'..=b'9') => {
+                num = num * 10 + (digit - b'0') as usize;
+                continue
+            }
+            Some(b';') => continue,
+            Some(b'A') => Key::Up,
+            Some(b'B') => Key::Down,
+            Some(b'C') => Key::Right,
+            Some(b'D') => Key::Left,
+            Some(b'H') => Key::Home,
+            Some(b'F') => Key::End,
+            Some(b'~') => match num {
+                1 | 7 => Key::Home,
+                4 | 8 => Key::End,
+                3     => Key::Delete,
+                _     => Key::Other
+            },
+            _ => Key::Other
+        };
+
+        return Ok(key)
+    }
+}
+
+/// A line read by `Editor`
+enum Line {
+    Text(String),
+    /// Ctrl-C, dropping the input
+    Cancel,
+    /// end of input, or Ctrl-D on an empty line
+    Eof
+}
+
+/// Reads input with line editing, history and completion
+///
+/// ## Note
+/// - arrows move along the line and through the history
+/// - Ctrl-A, Ctrl-E, Ctrl-K, Ctrl-U and Ctrl-W move and
+///   delete as in a shell, and Ctrl-L clears the screen
+/// - Ctrl-R searches the history, Ctrl-R again for an older
+///   match, Enter to accept it and Ctrl-G to give up
+/// - Tab completes the symbol before the cursor
+pub struct Editor {
+    history: Vec<String>,
+    file: Option<PathBuf>,
+    term: bool
+}
+
+impl Editor {
+    /// Makes an editor keeping its history in `file`, if any
+    pub fn new(file: Option<PathBuf>) -> Self {
+        let mut history: Vec<String> = file
+            .as_ref()
+            .and_then(|file| fs::read_to_string(file).ok())
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(unescape)
+            .collect();
+
+        // drops the oldest entries, so the file stops growing
+        if history.len() > HISTORY_LEN {
+            history.drain(..history.len() - HISTORY_LEN);
+
+            if let Some(file) = &file {
+                let lines: Vec<String> = history
+                    .iter()
+                    .map(|entry| escape(entry))
+                    .collect();
+
+                let _ = fs::write(file, lines.join("\n") + "\n");
+            }
+        }
+
+        Self {
+            history,
+            file,
+            term: io::stdin().is_terminal() && io::stdout().is_terminal()
+        }
+    }
+
+    /// Returns the history file in the home directory
+    pub fn default_history() -> Option<PathBuf> {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".jester_history"))
+    }
+
+    /// Reads an input, prompting `prompt` on its first line and
+    /// `more` on the next ones, until its brackets are balanced.
+    /// Returns `None` at the end of input
+    ///
+    /// ## Note
+    /// `complete` returns the words Tab completes from
+    pub fn read<F>(&mut self, prompt: &str, more: &str, complete: F) -> io::Result<Option<String>>
+    where
+        F: Fn() -> Vec<String>
+    {
+        let mut lines: Vec<String> = Vec::new();
+
+        loop {
+            let prompt = match lines.is_empty() {
+                true  => prompt,
+                false => more
+            };
+
+            let line = match self.term {
+                true  => self.edit(prompt, lines.is_empty(), &complete)?,
+                false => read_plain(prompt)?
+            };
+
+            let eof = matches!(line, Line::Eof);
+
+            match line {
+                Line::Text(line) => lines.push(line),
+                Line::Cancel => {
+                    lines.clear();
+                    continue
+                }
+                Line::Eof if lines.is_empty() => return Ok(None),
+                // reports the unbalanced input left at the end
+                Line::Eof => ()
+            }
+
+            let src = lines.join("\n");
+
+            if depth(&src) <= 0 || eof {
+                self.add_history(&src);
+                return Ok(Some(src))
+            }
+        }
+    }
+
+    /// Appends `src` to the history and its file,
+    /// unless it is empty or repeats the last entry
+    fn add_history(&mut self, src: &str) {
+        let entry = flatten(src);
+
+        if entry.is_empty() || self.history.last() == Some(&entry) {
+            return
+        }
+
+        if let Some(file) = &self.file {
+            let _ = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(file)
+                .and_then(|mut file| writeln!(file, "{}", escape(&entry)));
+        }
+
+        self.history.push(entry);
+    }
+
+    /// Edits a line in raw mode, where `first` is whether
+    /// it is the first line of the input
+    fn edit(&self, prompt: &str, first: bool, complete: &dyn Fn() -> Vec<String>) -> io::Result<Line> {
+        let raw = RawMode::enable()?;
+        let width = stty(&["size"])
+            .ok()
+            .and_then(|size| size.split(' ').nth(1)?.parse().ok())
+            .unwrap_or(WIDTH);
+
+        let mut state = State {
+            prompt,
+            width,
+            line: Vec::new(),
+            cursor: 0,
+            // the line being typed is past the last entry
+            entry: self.history.len(),
+            typed: Vec::new()
+        };
+
+        let mut input = io::stdin().lock();
+        state.draw()?;
+
+        let line = loop {
+            let key = match read_key(&mut input)? {
+                Some(key) => key,
+                None => break Line::Eof
+            };
+
+            let key = match key {
+                Key::Ctrl('r') => match self.search(&mut state, &mut input)? {
+                    Some(key) => key,
+                    None => continue
+                },
+                key => key
+            };
+
+            match key {
+                Key::Enter => break Line::Text(state.line.iter().collect()),
+                Key::Ctrl('c') => {
+                    print!("^C");
+                    break Line::Cancel
+                }
+                Key::Ctrl('d') if state.line.is_empty() && first => break Line::Eof,
+                Key::Tab => state.complete(&complete())?,
+                key => state.apply(key, &self.history)
+            }
+
+            state.draw()?;
+        };
+
+        print!("\r\n");
+        io::stdout().flush()?;
+        drop(raw);
+
+        Ok(line)
+    }
+
+    /// Searches the history backwards while keys are typed,
+    /// returning the key that accepted the match, if any
+    fn search(&self, state: &mut State, input: &mut impl Read) -> io::Result<Option<Key>> {
+        let mut query = String::new();
+        let mut found: Option<usize> = None;
+
+        loop {
+            let matched = found.map_or("", |i| self.history[i].as_str());
+
+            print!("\r(reverse-i-search)`{}': {}\x1b[K", query, matched);
+            io::stdout().flush()?;
+
+            // searches from before the match when asked again
+            let from = match read_key(input)? {
+                Some(Key::Char(ch)) => {
+                    query.push(ch);
+                    found.map_or(self.history.len(), |i| i + 1)
+                }
+                Some(Key::Backspace) => {
+                    query.pop();
+                    self.history.len()
+                }
+                Some(Key::Ctrl('r')) => found.unwrap_or(self.history.len()),
+                Some(Key::Ctrl('g')) | Some(Key::Ctrl('c')) => {
+                    state.draw()?;
+                    return Ok(None)
+                }
+                key => {
+                    if let Some(i) = found {
+                        state.set_line(&self.history[i]);
+                    }
+
+                    return Ok(key.or(Some(Key::Enter)))
+                }
+            };
+
+            found = self.history[..from]
+                .iter()
+                .rposition(|entry| entry.contains(&query))
+                .or(found.filter(|i| self.history[*i].contains(&query)));
+        }
+    }
+}
+
+/// A line being edited
+struct State<'a> {
+    prompt: &'a str,
+    width: usize,
+    line: Vec<char>,
+    cursor: usize,
+    /// history entry shown, or the history length
+    entry: usize,
+    /// the line typed before browsing the history
+    typed: Vec<char>
+}
+
+impl State<'_> {
+    /// Redraws the line, scrolled to keep the cursor in sight
+    fn draw(&self) -> io::Result<()> {
+        let room = self.width
+            .saturating_sub(self.prompt.chars().count() + 1)
+            .max(1);
+
+        let beg = self.cursor.saturating_sub(room - 1);
+        let end = self.line.len().min(beg + room);
+        // the newlines of multi-line strings take a column each
+        let shown: String = self.line[beg..end]
+            .iter()
+            .map(|ch| match ch {
+                '\n' | '\r' => '↵',
+                '\t' => ' ',
+                ch => *ch
+            })
+            .collect();
+        let col = self.prompt.chars().count() + self.cursor - beg;
+
+        print!("\r{}{}\x1b[K\r", self.prompt, shown);
+
+        if col > 0 {
+            print!("\x1b[{}C", col);
+        }
+
+        io::stdout().flush()
+    }
+
+    fn set_line(&mut self, line: &str) {
+        self.line = line.chars().collect();
+        self.cursor = self.line.len();
+    }
+
+    fn apply(&mut self, key: Key, history: &[String]) {
+        let line = &mut self.line;
+
+        match key {
+            Key::Char(ch) => {
+                line.insert(self.cursor, ch);
+                self.cursor += 1;
+            }
+            Key::Backspace | Key::Ctrl('h') if self.cursor > 0 => {
+                self.cursor -= 1;
+                line.remove(self.cursor);
+            }
+            Key::Delete | Key::Ctrl('d') if self.cursor < line.len() => {
+                line.remove(self.cursor);
+            }
+            Key::Left | Key::Ctrl('b') => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right | Key::Ctrl('f') => self.cursor = line.len().min(self.cursor + 1),
+            Key::Home | Key::Ctrl('a') => self.cursor = 0,
+            Key::End | Key::Ctrl('e') => self.cursor = line.len(),
+            Key::Ctrl('k') => line.truncate(self.cursor),
+            Key::Ctrl('u') => {
+                line.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            Key::Ctrl('w') => {
+                let beg = line[..self.cursor]
+                    .iter()
+                    .rposition(|ch| !ch.is_whitespace())
+                    .and_then(|end| line[..end].iter().rposition(|ch| ch.is_whitespace()))
+                    .map_or(0, |space| space + 1);
+
+                line.drain(beg..self.cursor);
+                self.cursor = beg;
+            }
+            Key::Ctrl('l') => print!("\x1b[2J\x1b[H"),
+            Key::Up | Key::Ctrl('p') if self.entry > 0 => {
+                if self.entry == history.len() {
+                    self.typed = line.clone();
+                }
+
+                self.entry -= 1;
+                self.set_line(&history[self.entry]);
+            }
+            Key::Down | Key::Ctrl('n') if self.entry < history.len() => {
+                self.entry += 1;
+
+                match history.get(self.entry) {
+                    Some(entry) => self.set_line(entry),
+                    None => {
+                        self.line = std::mem::take(&mut self.typed);
+                        self.cursor = self.line.len();
+                    }
+                }
+            }
+            _ => ()
+        }
+    }
+
+    /// Completes the word before the cursor from `words`,
+    /// listing them if it can't be completed any further
+    fn complete(&mut self, words: &[String]) -> io::Result<()> {
+        let beg = self.line[..self.cursor]
+            .iter()
+            .rposition(|ch| BREAKS.contains(*ch))
+            .map_or(0, |brk| brk + 1);
+
+        let prefix: String = self.line[beg..self.cursor].iter().collect();
+
+        if prefix.is_empty() {
+            return Ok(())
+        }
+
+        let found: Vec<&String> = words
+            .iter()
+            .filter(|word| word.starts_with(&prefix))
+            .collect();
+
+        let common = match found.split_first() {
+            Some((first, rest)) => rest.iter().fold(first.as_str(), |common, word| {
+                let len = common
+                    .char_indices()
+                    .zip(word.chars())
+                    .find(|((_, lhs), rhs)| lhs != rhs)
+                    .map_or(common.len().min(word.len()), |((i, _), _)| i);
+
+                &common[..len]
+            }),
+            None => {
+                print!("\x07");
+                return Ok(())
+            }
+        };
+
+        if common.len() > prefix.len() || found.len() == 1 {
+            let rest = common[prefix.len()..].chars();
+            let space = (found.len() == 1).then_some(' ');
+
+            for ch in rest.chain(space) {
+                self.line.insert(self.cursor, ch);
+                self.cursor += 1;
+            }
+
+            return Ok(())
+        }
+
+        let shown: Vec<&str> = found
+            .iter()
+            .take(100)
+            .map(|word| word.as_str())
+            .collect();
+
+        print!("\r\n{}", shown.join("  "));
+
+        if found.len() > shown.len() {
+            print!("  ..{} more", found.len() - shown.len());
+        }
+
+        print!("\r\n");
+        Ok(())
+    }
+}
+
+/// Reads a line from input that isn't a terminal
+fn read_plain(prompt: &str) -> io::Result<Line> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+
+    match io::stdin().lock().read_line(&mut line)? {
+        0 => Ok(Line::Eof),
+        _ => Ok(Line::Text(line.trim_end_matches(['\n', '\r']).to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flatten_keeps_strings() {
+        assert_eq!(flatten("(println   \"a   b\")"), "(println \"a   b\")");
+        assert_eq!(flatten("(defun sq (x)\n\t(* x x)) ; squares"), "(defun sq (x) (* x x))");
+        assert_eq!(flatten("(print \"a\nb\")"), "(print \"a\nb\")");
+    }
+
+    #[test]
+    fn entries_round_trip_the_file() {
+        for entry in ["(print \"a\nb\")", "(print \"\\\\n\")", "(+ 1 2)"] {
+            let line = escape(entry);
+
+            assert!(!line.contains('\n'));
+            assert_eq!(unescape(&line), entry);
+        }
+    }
+}
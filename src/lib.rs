@@ -0,0 +1,43 @@
+//! Jester Script, a Lisp-inspired scripting language
+//! 
+//! ## Example
+//! ```
+//! use jester::{Env, Obj, TypeId};
+//! 
+//! # fn main() -> jester::Err {
+//! let env = Env::builder()
+//!     .bridge("double", |env, args| {
+//!         let x = args.get(0)?.eval(env)?.is_num()?;
+//!         Ok((x * 2.0).as_obj())
+//!     })
+//!     .build()?;
+//! 
+//! env.eval_str("(defun quad (x) (double (double x)))")?;
+//! 
+//! let res = env.call("quad", vec![Obj::from(5)])?;
+//! assert_eq!(*res.is_f64()?, 20.0);
+//! # Ok(())
+//! # }
+//! ```
+
+mod core;
+mod prelude;
+
+pub use crate::core::{
+    env::Env,
+    builder::{EnvBuilder, Lib},
+    obj::Obj,
+    err::{Err, ErrType, ErrObj, HostErr, Backtrace, CallSite},
+    lex::Span,
+    load::{PATH_VAR, SCRIPT_EXT},
+    map::{Map, Key},
+    module::{Module, PATH_SEP},
+    node::{Node, NodeIter},
+    fun::{Bridge, BridgeMut, Callable, FnBridge, FnMacro, FnNative, HostFn, Tail, TailBridge},
+    rc_cell::RcCell,
+    scope::Scope,
+    sync::{ThreadSafe, ReadGuard, WriteGuard},
+    gc::LiveObjects,
+    limit::{Limits, CancelHandle},
+    type_id::{TypeId, FromObj, IntoObj, Primitive, Numeric}
+};
@@ -1,12 +1,158 @@
-mod core;
-mod prelude;
+mod editor;
+mod repl;
 
-use crate::core::{
-    env::Env,
-    err::Err,
-};
+use jester::{Env, Err, Limits, Node, Obj};
 
-fn main() -> Err {
-    let mut env = Env::new()?;
-    env.repl()
+/// Usage of the command line, printed by `--help`
+const USAGE: &str = "\
+usage: jester [options] [script [args...]]
+
+Runs script, searched for like (load script), or the REPL if there is none
+
+options:
+  -e expr        evaluates expr and prints its value, may be repeated,
+                 every arg after the options is then passed to *args*
+  -i             starts the REPL once script and exprs have run
+  --no-prelude   starts without the prelude (for, range, filter ..)
+  -h, --help     prints this message
+  --             ends the options
+
+The args after script are passed to it as a list of strings in *args*";
+
+/// Symbol holding the args passed to the script
+const ARGS_SYM: &str = "*args*";
+
+/// Deepest evaluation scripts may nest, so runaway 
+/// recursion fails with an error instead of a crash
+const MAX_DEPTH: usize = 4_000;
+/// Stack of the thread running scripts, which fits
+/// `MAX_DEPTH` evaluations even in debug builds
+const STACK_SIZE: usize = 256 << 20;
+
+/// What the command line asked for
+#[derive(Default)]
+struct Opts {
+    script: Option<String>,
+    exprs: Vec<String>,
+    args: Vec<String>,
+    interactive: bool,
+    no_prelude: bool,
+    help: bool
+}
+
+impl Opts {
+    /// Parses the command line args, without the program name
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut opts = Opts::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-e" => match args.next() {
+                    Some(expr) => opts.exprs.push(expr),
+                    None => return Err("-e needs an expression".to_string())
+                },
+                "-i" => opts.interactive = true,
+                "--no-prelude" => opts.no_prelude = true,
+                "-h" | "--help" => opts.help = true,
+                "--" => {
+                    opts.push_arg(args.next());
+                    break;
+                }
+                flag if flag.starts_with('-') && flag != "-" => {
+                    return Err(format!("unknown option '{}'", flag))
+                }
+                _ => {
+                    opts.push_arg(Some(arg));
+                    break;
+                }
+            }
+        }
+
+        opts.args.extend(args);
+        Ok(opts)
+    }
+
+    /// Takes `arg` as the script, or as an arg
+    /// to the exprs if any were given
+    fn push_arg(&mut self, arg: Option<String>) {
+        match arg {
+            Some(arg) if self.exprs.is_empty() => self.script = Some(arg),
+            Some(arg) => self.args.push(arg),
+            None => ()
+        }
+    }
+
+    /// Makes the `Env` scripts, exprs and the REPL run in
+    fn build(&self) -> Err<Env> {
+        let env = Env::builder()
+            .prelude(!self.no_prelude)
+            .limits(Limits {
+                depth: Some(MAX_DEPTH),
+                ..Limits::default()
+            })
+            .build()?;
+
+        let args = self.args
+            .iter()
+            .map(|arg| Obj::from(arg.as_str()))
+            .collect::<Node>();
+
+        env.set(ARGS_SYM, Obj::Lst(args))?;
+        Ok(env)
+    }
+}
+
+fn run(opts: Opts) -> Err {
+    if opts.interactive || (opts.script.is_none() && opts.exprs.is_empty()) {
+        let start: Vec<String> = opts.script
+            .iter()
+            .map(|script| format!(":load {}", script))
+            .chain(opts.exprs.iter().cloned())
+            .collect();
+
+        return repl::repl(|| opts.build(), &start)
+    }
+
+    let env = opts.build()?;
+
+    if let Some(script) = &opts.script {
+        env.load_file(script)?;
+    }
+
+    for expr in opts.exprs.iter() {
+        let obj = env.eval_str(expr)?;
+        println!("{}", obj.display(&env));
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let opts = match Opts::parse(std::env::args().skip(1)) {
+        Ok(opts) => opts,
+        Err(msg) => {
+            eprintln!("error: {}\n\n{}", msg, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if opts.help {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let runner = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || run(opts).map_err(|err| err.to_string()))
+        .expect("couldn't start the script thread");
+
+    // a panic was already reported by the thread
+    let res = runner
+        .join()
+        .unwrap_or_else(|_| std::process::exit(101));
+
+    if let Err(err) = res {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,181 @@
+use crate::core::{
+    env::Env,
+    obj::Obj,
+    node::{Node, NodeIter},
+    rc_cell::RcCell,
+    scope::Scope,
+    type_id::TypeId,
+    err::{Err, ErrObj, ErrType::*}
+};
+
+/// Evaluates each of `body`, returning `nil` if empty
+fn progn_or_nil(env: &Env, body: NodeIter) -> Err<Obj> {
+    match body.is_empty() {
+        true => Ok(Obj::Nil(())),
+        false => body.progn(|obj| env.eval(&obj.as_ref()))
+    }
+}
+
+/// Takes the clause headed by `sym` off the end of `args[..*end]`, if any
+fn clause(args: &NodeIter, end: &mut usize, sym: &RcCell<Obj>) -> Option<Node> {
+    let last = args.get_cell(end.checked_sub(1)?).ok()?;
+
+    match &*last.as_ref() {
+        Obj::Lst(node) => match node.get(0).as_deref() {
+            Ok(Obj::Sym(head)) if head.raw_eq(sym) => {
+                *end -= 1;
+                Some(node.clone())
+            }
+            _ => None
+        },
+        _ => None
+    }
+}
+
+impl Env {
+    pub fn err_lib(&mut self) {
+
+        // (catch err ..handler) - only valid within try
+        self.add_bridge("catch", |_, _| {
+            Err(MisForm("catch outside of try".to_string()))
+        });
+
+        // (finally ..cleanup) - only valid within try
+        self.add_bridge("finally", |_, _| {
+            Err(MisForm("finally outside of try".to_string()))
+        });
+
+        // (try ..body &optional (catch err ..handler) (finally ..cleanup))
+        self.add_bridge("try", |env, args| {
+            let catch = env.get_sym("catch").unwrap();
+            let finally = env.get_sym("finally").unwrap();
+
+            // clauses trail the body, catch before finally
+            let mut body = args.len();
+            let cleanup = clause(&args, &mut body, &finally);
+            let handler = clause(&args, &mut body, &catch);
+
+            let res = (0..body)
+                .map(|i| args.get(i))
+                .try_fold(Obj::Nil(()), |_, obj| env.eval(&*obj?));
+
+            let res = match (res, handler) {
+                (Err(err), Some(handler)) if !err.is_limit() => {
+                    let sym = handler.get(1)?;
+
+                    let mut scope = Scope::new(env.scope());
+                    scope.bind(sym.is_symbol()?, Obj::Error(err.into_obj()))?;
+
+                    env.in_scope(RcCell::from(scope), || {
+                        progn_or_nil(env, handler.iter_from(2))
+                    })
+                }
+                (res, _) => res
+            };
+
+            if let Some(cleanup) = cleanup {
+                progn_or_nil(env, cleanup.iter_from(1))?;
+            }
+
+            res
+        });
+
+        // (unwind-protect protected ..cleanup)
+        self.add_bridge("unwind-protect", |env, args| {
+            let res = args.get(0)?.eval(env);
+            progn_or_nil(env, args.shift())?;
+            res
+        });
+
+        // (throw payload)
+        self.add_bridge("throw", |env, args| {
+            let payload = args.get(0)?.eval(env)?;
+
+            // rethrows a caught error as is
+            if let Obj::Error(err) = payload {
+                return Err(Thrown(err))
+            }
+
+            let message = payload.as_string(env);
+            Err(Thrown(ErrObj::new(ErrObj::THROWN, message, payload)))
+        });
+
+        // (error-kind err)
+        self.register("error-kind", |err: ErrObj| {
+            err.kind().to_string()
+        });
+
+        // (error-message err)
+        self.register("error-message", |err: ErrObj| {
+            err.message().to_string()
+        });
+
+        // (error-payload err)
+        self.register("error-payload", |err: ErrObj| {
+            err.payload().clone()
+        });
+
+        // (error-location err) - "file:line:col" or nil
+        self.register("error-location", |err: ErrObj| {
+            match err.span() {
+                Some(span) => span.to_string().as_obj(),
+                None => Obj::Nil(())
+            }
+        });
+
+        // (error-trace err) - called function names, innermost first
+        self.register("error-trace", |err: ErrObj| {
+            err
+                .trace()
+                .map(|trace| trace.calls.iter())
+                .into_iter()
+                .flatten()
+                .map(|call| call.name.clone().as_obj())
+                .collect::<Node>()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::env::Env;
+
+    /// Evaluates `src` after defining `note`, which appends its
+    /// arg to `log`, returning the value of `src` and the log
+    fn eval(src: &str) -> String {
+        let env = Env::new().unwrap();
+        env.eval_str("(set log ()) (defun note (x) (append x log))").unwrap();
+
+        let obj = env.eval_str(src).unwrap();
+        let log = env.eval_str("log").unwrap();
+
+        format!("{} {}", obj.display(&env), log.display(&env))
+    }
+
+    #[test]
+    fn catch_runs_before_finally() {
+        let src = "(try (note 1) (throw 42) (note 99) (catch e (note (error-payload e)) 0) (finally (note 3)))";
+        assert_eq!(eval(src), "0 (1 42 3)");
+
+        assert_eq!(eval("(try (note 1) (finally (note 2)))"), "1 (1 2)");
+    }
+
+    #[test]
+    fn cleanup_runs_before_outer_handlers() {
+        let src = "(try (try (note 1) (/ 1 0) (finally (note 2))) (catch e (note (error-kind e))))";
+        assert_eq!(eval(src), "\"div-by-zero\" (1 2 div-by-zero)");
+
+        let src = "(try (unwind-protect (throw 7) (note 8)) (catch e (note (error-payload e))))";
+        assert_eq!(eval(src), "7 (8 7)");
+    }
+
+    #[test]
+    fn throws_from_handlers_reach_outer_handlers() {
+        let src = "
+            (try
+                (try (throw 1) (catch e (note \"c\") (throw 2)) (finally (note \"f\")))
+                (catch e (note (error-payload e))))";
+
+        assert_eq!(eval(src), "2 (c f 2)");
+    }
+}
@@ -1,4 +1,9 @@
-use crate::core::{env::Env, err::ErrType::RuntimeAssert, type_id::TypeId};
+use crate::core::{
+    env::Env, 
+    obj::Obj,
+    err::ErrType::RuntimeAssert, 
+    type_id::TypeId
+};
 
 impl Env {
     pub fn io_lib(&mut self) {
@@ -30,6 +35,25 @@ impl Env {
             })
         });
 
+        // (load path)
+        self.add_bridge("load", |env, args| {
+            let path = args
+                .get(0)?
+                .eval(env)?;
+
+            env.load_file(path.is_string()?)
+        });
+
+        // (require name)
+        self.add_bridge("require", |env, args| {
+            let name = match args.get(0)?.eval(env)? {
+                Obj::Sym(sym) => env.get_sym_name(&sym).unwrap(),
+                obj => obj.is_string()?.clone()
+            };
+
+            env.require(&name)
+        });
+
         // (format source ..items)
         self.add_bridge("format", |env, args| {
             const PAT: &str = "{}";
@@ -52,7 +76,7 @@ impl Env {
                         source = source[0..beg].replace(PAT, &format) + &source[beg..]; 
                     }
 
-                    None => return Err(RuntimeAssert)
+                    None => return Err(RuntimeAssert("too many arguments to format".to_string()))
                 }
             }
 
@@ -1,5 +1,7 @@
 use crate::core::{
     env::Env,
+    obj::Obj,
+    err::ErrType::OutOfBound,
     rc_cell::RcCell, 
     type_id::TypeId
 };
@@ -7,44 +9,63 @@ use crate::core::{
 impl Env {
     pub fn list_lib(&mut self) {
 
+        // (list ..items)
+        self.add_bridge("list", |env, args| {
+            args.mapped(|obj| env.eval(&obj.as_ref()))
+                .map(Obj::Lst)
+        });
+
+        // (vector ..items)
+        self.add_bridge("vector", |env, args| {
+            args.mapped(|obj| env.eval(&obj.as_ref()))
+                .map(Obj::Vct)
+        });
+
         // (len list)
-        self.add_bridge("len", |_, args| {
-            let len = args
-                .get(0)?
-                .val()
-                .is_node()?
-                .len();
+        self.add_bridge("len", |env, args| {
+            let len = match &*env.eval_cell(args.get_cell(0)?)?.as_ref() {
+                Obj::Map(map) => map.len(),
+                obj => obj.is_node()?.len()
+            };
 
             Ok((len as i64).as_obj())
         });
 
-        // (nth list)
+        // (nth index list)
         self.add_bridge("nth", |env, args| {
             let idx = args
                 .get(0)?
                 .eval(env)?
-                .is_int()?;
+                .is_int()? as usize;
             
-            Ok(args
-                .get_mut(1)?
-                .val_mut()
-                .is_node_mut()?
-                .get_cell(idx as usize)?
-                .clone_inner())
+            let list = env.eval_cell(args.get_cell(1)?)?;
+
+            let elem = match &*list.as_ref() {
+                // maps are indexed by entry, as (key value) lists
+                Obj::Map(map) => map
+                    .entry(idx)
+                    .map(Obj::Lst)
+                    .ok_or(OutOfBound { index: idx, len: map.len() }),
+                obj => Ok(obj
+                    .is_node()?
+                    .get_cell(idx)?
+                    .clone_inner())
+            };
+
+            elem
         });
 
         // (replace index value list)
         self.add_bridge("replace", |env, args| {
             let [index, value] = env.eval_args([0, 1], args)?;
 
-            let list = args
-                .get_mut(2)?
-                .val_mut()
-                .is_node()?;
+            let list = env.eval_cell(args.get_cell(2)?)?;
+            let list = list.as_ref();
+            let list = list.is_node()?;
 
-            let elem = list.get_mut(index.is_int()? as usize)?;
-            let copy = elem.clone();
-            *elem = value;
+            let copy = list
+                .get_cell(index.is_int()? as usize)?
+                .replace(value)?;
                         
             Ok(copy)
         });
@@ -53,12 +74,11 @@ impl Env {
         self.add_bridge("append", |env, args| {     
             let value = args
                 .get(0)?
-                .eval(&env)?;
+                .eval(env)?;
 
-            let list = args
-                .get_mut(1)?
-                .val_mut()
-                .is_node_mut()?;
+            let list = env.eval_cell(args.get_cell(1)?)?;
+            let mut list = list.as_mut()?;
+            let list = list.is_node_mut()?;
 
             list.push(RcCell::from(value.clone()));
 
@@ -72,10 +92,9 @@ impl Env {
                 .eval(env)?
                 .clone();
 
-            let list = args
-                .get_mut(1)?
-                .val_mut()
-                .is_node_mut()?;
+            let list = env.eval_cell(args.get_cell(1)?)?;
+            let mut list = list.as_mut()?;
+            let list = list.is_node_mut()?;
 
             list.insert(0, RcCell::from(item.clone()))?;        
             Ok(item)
@@ -85,10 +104,9 @@ impl Env {
         self.add_bridge("insert", |env, args| {
             let [index, item] = env.eval_args([0, 1], args)?;
 
-            let list = args
-                .get_mut(2)?
-                .val_mut()
-                .is_node_mut()?;
+            let list = env.eval_cell(args.get_cell(2)?)?;
+            let mut list = list.as_mut()?;
+            let list = list.is_node_mut()?;
 
             list.insert(index.is_int()? as usize, RcCell::from(item.clone()))?;  
             Ok(item)
@@ -101,13 +119,45 @@ impl Env {
                 .eval(env)?
                 .is_int()? as usize;
 
-            let list = args
-                .get_mut(1)?
-                .val_mut()
-                .is_node_mut()?;
+            let list = env.eval_cell(args.get_cell(1)?)?;
+            let mut list = list.as_mut()?;
+            let list = list.is_node_mut()?;
 
             let rem = list.remove(index)?;        
             Ok(rem)
         });
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use crate::core::env::Env;
+
+    /// Evaluates `src` in a new `Env`, returning its value as printed
+    fn eval(src: &str) -> String {
+        let env = Env::new().unwrap();
+        let obj = env.eval_str(src).unwrap();
+
+        obj.display(&env)
+    }
+
+    #[test]
+    fn len_and_nth_eval_their_list() {
+        assert_eq!(eval("(len [1 2])"), "2");
+        assert_eq!(eval("(nth 0 [5 6])"), "5");
+        assert_eq!(eval("(len (range 0 to 5))"), "5");
+        assert_eq!(eval("(nth 0 (range 0 to 5))"), "0");
+        assert_eq!(eval("(let (s 0) (for-each x in [1 2 3] (set s (+ s x))) s)"), "6");
+    }
+
+    #[test]
+    fn len_and_nth_walk_map_entries() {
+        assert_eq!(eval("(len {1 2})"), "1");
+        assert_eq!(eval("(nth 0 {1 2})"), "(1 2)");
+        assert_eq!(eval("(keys {\"ann\" 31 \"cat\" 4})"), "(ann cat)");
+    }
+
+    #[test]
+    fn append_changes_a_symbol_in_place() {
+        assert_eq!(eval("(set l (list 1)) (append 2 l) l"), "(1 2)");
+    }
+}
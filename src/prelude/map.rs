@@ -0,0 +1,133 @@
+use crate::core::{
+    env::Env,
+    obj::Obj,
+    map::{Map, Key},
+    node::NodeIter,
+    rc_cell::RcCell,
+    err::{Err, ErrType::*},
+    type_id::TypeId
+};
+
+impl Env {
+    /// Returns the cell holding the map at `args[i]`, the
+    /// binding of a symbol or else the evaluated form
+    fn map_arg(&self, args: NodeIter, i: usize) -> Err<RcCell<Obj>> {
+        let cell = match &*args.get(i)? {
+            Obj::Sym(sym) => self.lookup(sym),
+            obj => RcCell::from(obj.eval(self)?)
+        };
+
+        cell.as_ref().is_map()?;
+        Ok(cell)
+    }
+
+    pub fn map_lib(&mut self) {
+
+        // (hash-map ..pairs)
+        self.add_bridge("hash-map", |env, args| {
+            if args.len() % 2 != 0 {
+                return Err(MisForm("hash-map expects key value pairs".to_string()))
+            }
+
+            let mut map = Map::default();
+            let keys = args.step_by(2);
+            let vals = args.skip(1).step_by(2);
+
+            for (key, val) in keys.zip(vals) {
+                let key = Key::new(key.as_ref().eval(env)?)?;
+                map.insert(key, val.as_ref().eval(env)?);
+            }
+
+            Ok(map.as_obj())
+        });
+
+        // (get key map &optional default)
+        self.add_bridge("get", |env, args| {
+            let key = Key::new(args.get(0)?.eval(env)?)?;
+            let map = env.map_arg(args, 1)?;
+
+            if let Some(val) = map.as_ref().is_map()?.get(&key) {
+                return Ok(val.clone())
+            }
+
+            match args.get(2) {
+                Ok(default) => default.eval(env),
+                Err(_) => Ok(Obj::Nil(()))
+            }
+        });
+
+        // (put key value map)
+        self.add_bridge("put", |env, args| {
+            let [key, val] = env.eval_args([0, 1], args)?;
+            let key = Key::new(key)?;
+            let map = env.map_arg(args, 2)?;
+
+            map.as_mut()?
+                .is_map_mut()?
+                .insert(key, val.clone());
+
+            Ok(val)
+        });
+
+        // (has? key map)
+        self.add_bridge("has?", |env, args| {
+            let key = Key::new(args.get(0)?.eval(env)?)?;
+            let map = env.map_arg(args, 1)?;
+
+            let has = map.as_ref().is_map()?.contains(&key);
+            Ok(has.as_obj())
+        });
+
+        // (keys map)
+        self.add_bridge("keys", |env, args| {
+            let map = env.map_arg(args, 0)?;
+            let map = map.as_ref();
+
+            Ok(Obj::Lst(map
+                .is_map()?
+                .keys()
+                .map(|key| key.as_obj().clone())
+                .collect()))
+        });
+
+        // (vals map)
+        self.add_bridge("vals", |env, args| {
+            let map = env.map_arg(args, 0)?;
+            let map = map.as_ref();
+
+            Ok(Obj::Lst(map
+                .is_map()?
+                .vals()
+                .cloned()
+                .collect()))
+        });
+
+        // (remove-key key map)
+        self.add_bridge("remove-key", |env, args| {
+            let key = Key::new(args.get(0)?.eval(env)?)?;
+            let map = env.map_arg(args, 1)?;
+
+            let rem = map
+                .as_mut()?
+                .is_map_mut()?
+                .remove(&key);
+
+            Ok(rem.unwrap_or(Obj::Nil(())))
+        });
+
+        // (merge ..maps)
+        self.add_bridge("merge", |env, args| {
+            let mut merged = Map::default();
+
+            for i in 0..args.len() {
+                let map = env.map_arg(args, i)?;
+
+                for (key, val) in map.as_ref().is_map()?.iter() {
+                    merged.insert(key.clone(), val.clone());
+                }
+            }
+
+            Ok(merged.as_obj())
+        });
+    }
+}
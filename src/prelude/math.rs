@@ -7,10 +7,10 @@ impl Env {
     pub fn math_lib(&mut self) {
 
         // constant E = 2.718
-        self.add_primitive("E",  2.71828);
+        self.add_primitive("E",  std::f64::consts::E);
         
         // constant PI = 3.141
-        self.add_primitive("Pi", 3.14159);        
+        self.add_primitive("Pi", std::f64::consts::PI);        
 
         // (+ first ..rest)
         self.add_bridge("+", |env, args| {
@@ -1,4 +1,7 @@
 pub mod math;
 pub mod std;
 pub mod io;
-pub mod list;
\ No newline at end of file
+pub mod list;
+pub mod map;
+pub mod module;
+pub mod err;
\ No newline at end of file
@@ -0,0 +1,71 @@
+use crate::core::{
+    env::Env,
+    obj::Obj,
+    rc_cell::RcCell,
+    err::{Err, ErrType::*}
+};
+
+impl Env {
+    /// Returns the unqualified name of symbol `obj`
+    fn sym_name(&self, obj: &Obj) -> Err<String> {
+        Ok(self.get_item_name(obj.is_symbol()?).unwrap())
+    }
+
+    /// Returns the export and name to import it as, from
+    /// either `item` or `(item as name)`
+    fn import_item(&self, obj: &Obj, as_sym: &RcCell<Obj>) -> Err<(String, String)> {
+        if let Obj::Lst(node) = obj {
+            return match (node.len(), &*node.get(1)?) {
+                (3, Obj::Sym(sym)) if sym.raw_eq(as_sym) => {
+                    Ok((self.sym_name(&*node.get(0)?)?, self.sym_name(&*node.get(2)?)?))
+                }
+                _ => Err(MisForm(obj.as_string(self)))
+            }
+        }
+
+        let item = self.sym_name(obj)?;
+        Ok((item.clone(), item))
+    }
+
+    pub fn module_lib(&mut self) {
+
+        // (export ..items) - only valid within module
+        self.add_bridge("export", |_, _| {
+            Err(MisForm("export outside of module".to_string()))
+        });
+
+        // (module name ..body)
+        self.add_bridge("module", |env, args| {
+            let name = env.sym_name(&*args.get(0)?)?;
+            env.define_module(&name, args.shift())
+        });
+
+        // (import name &optional as alias | (..items))
+        self.add_bridge("import", |env, args| {
+            let name = env.sym_name(&*args.get(0)?)?;
+            let as_sym = env.intern_sym("as");
+
+            match (args.len(), args.get(1).as_deref()) {
+                (1, _) => env.import_module(&name, None, None)?,
+
+                (2, Ok(Obj::Lst(items))) => {
+                    let items = items
+                        .iter()
+                        .map(|item| env.import_item(&item.as_ref(), &as_sym))
+                        .collect::<Err<_>>()?;
+
+                    env.import_module(&name, None, Some(items))?
+                }
+
+                (3, Ok(Obj::Sym(sym))) if sym.raw_eq(&as_sym) => {
+                    let alias = env.sym_name(&*args.get(2)?)?;
+                    env.import_module(&name, Some(&alias), None)?
+                }
+
+                _ => return Err(MisForm("expected (import name), (import name as alias) or (import name (..items))".to_string()))
+            }
+
+            Ok(Obj::Nil(()))
+        });
+    }
+}
@@ -3,9 +3,10 @@ use crate::core::{
     obj::Obj,
     node::Node, 
     rc_cell::RcCell,
+    scope::Scope,
     type_id::TypeId, 
     err::ErrType::*, 
-    fun::{FnNative, FnMacro},
+    fun::{FnNative, FnMacro, Tail},
 };
 
 impl Env {
@@ -26,26 +27,21 @@ impl Env {
                 .get(1)?
                 .eval(env)?;
 
-            args
-                .get_cell(0)?
-                .as_mut()
-                .assign(&rhs);
+            let lhs = env.lookup(args.get(0)?.is_symbol()?);
+            lhs.replace(rhs.clone())?;
             
             Ok(rhs)
         });
 
         // (gen-sym &optional val)
         self.add_bridge("gen-sym", |env, args| {            
-            unsafe {
-                let val;
-                match args.get(0) {
-                    Ok(opt) => val = opt.eval(env)?,
-                    _ => val = Obj::Nil(())
-                }
+            let val = match args.get(0) {
+                Ok(opt) => opt.eval(env)?,
+                _ => Obj::Nil(())
+            };
 
-                let sym = env.gen_sym_runtime(val);
-                Ok(sym.as_obj())
-            }
+            let sym = env.gen_sym(val);
+            Ok(sym.as_obj())
         });
 
         // (loop cond ..body)
@@ -55,7 +51,7 @@ impl Env {
             while *args.get(0)?.eval(env)?.is_bool()? {
                 ret = args
                     .shift()
-                    .progn(|obj| env.eval(obj.as_ref()))?;
+                    .progn(|obj| env.eval(&obj.as_ref()))?;
             }
             
             Ok(ret)
@@ -79,8 +75,8 @@ impl Env {
                 .cloned()
                 .collect();
 
-            let native = FnNative::new(name, params, body, false);
-            sym.as_mut().assign_to(native);
+            let native = FnNative::new(name, params, body, false, env.scope());
+            sym.as_ref().assign_to(native)?;
 
             Ok(sym.as_ref().clone())
         });
@@ -103,14 +99,14 @@ impl Env {
                 .cloned()
                 .collect();
 
-            let native = FnNative::new(name, params, body, true);
-            sym.as_mut().assign_to(native);
+            let native = FnNative::new(name, params, body, true, env.scope());
+            sym.as_ref().assign_to(native)?;
 
             Ok(sym.as_ref().clone())
         });
 
         // (lambda params ..body)
-        self.add_bridge("lambda", |_, args| {
+        self.add_bridge("lambda", |env, args| {
             let params = args
                 .get(0)?
                 .is_node()?
@@ -122,34 +118,39 @@ impl Env {
                 .collect();
             
             let name = Env::unique_sym();
-            let native = FnNative::new(name, params, body, false);
+            let native = FnNative::new(name, params, body, false, env.scope());
 
             Ok(native.as_obj())
         });
 
         // (let params ..body)
-        self.add_bridge("let", |env, args| {
-            let fst = args.get(0)?;
+        self.add_tail_bridge("let", |env, args| {
+            let vars = args.get(0)?;
+            let vars = vars.is_node()?;
+            let scope = RcCell::from(Scope::binding(env.scope()));
 
-            let params = fst
-                .is_node()?
-                .iter()
-                .step_by(2);
+            // the caller restores its scope once the body is evaluated
+            env.set_scope(Some(scope.clone()));
 
-            let inputs = fst
-                .is_node()?
-                .iter()
-                .skip(1)
-                .step_by(2);
+            let params = vars.iter().step_by(2);
+            let inputs = vars.iter().skip(1).step_by(2);
+            
+            // bind in order, so each input sees the previous params
+            for (param, input) in params.zip(inputs) {
+                let val = input.as_ref().eval(env)?;
+                scope.as_mut()?.bind(param.as_ref().is_symbol()?, val)?;
+            }
+
+            scope.as_mut()?.seal();
 
             args
                 .shift()
-                .progn_scoped(env, params, inputs)
+                .progn_tail(env)
         });
 
         // (do ..body)
-        self.add_bridge("do", |env, args| {
-            args.progn(|obj| env.eval(obj.as_ref()))
+        self.add_tail_bridge("do", |env, args| {
+            args.progn_tail(env)
         });
 
         // (defmacro params ..body)
@@ -170,8 +171,8 @@ impl Env {
                 .cloned()
                 .collect();
 
-            let native = FnMacro::new(name, params, body, false);
-            sym.as_mut().assign_to(native);
+            let native = FnMacro::new(name, params, body, false, env.scope());
+            sym.as_ref().assign_to(native)?;
 
             Ok(sym.as_ref().clone())
         });
@@ -194,29 +195,39 @@ impl Env {
                 .cloned()
                 .collect();
 
-            let native = FnMacro::new(name, params, body, true);
-            sym.as_mut().assign_to(native);
+            let native = FnMacro::new(name, params, body, true, env.scope());
+            sym.as_ref().assign_to(native)?;
 
             Ok(sym.as_ref().clone())
         });
 
         // (macro-expand macro)
         self.add_bridge("macro-expand", |env, node| {
-            let mac = node
-                .get(0)?
-                .val()
-                .is_node()?;
+            let form = env
+                .resolve(node.get_cell(0)?)
+                .clone_inner();
+
+            let mac = form.is_node()?;
 
-            match mac.get(0)?.eval(env)? {
+            let head = mac.get(0)?.eval(env)?;
+
+            match head {
                 Obj::Macro(f) => f.expand(env, mac.iter_from(1)),   
-                _ => Err(MisType)        
-            }     
+                obj => Err(obj.mis_type(FnMacro::type_str()))
+            }
+        });
+
+        // (disassemble fn)
+        self.add_bridge("disassemble", |env, args| {
+            match args.get(0)?.eval(env)? {
+                Obj::Native(f) => Ok(f.chunk(env).disassemble(env).as_obj()),
+                obj => Err(obj.mis_type(FnNative::type_str()))
+            }
         });
 
         // (type-of item)
-        self.add_bridge("type-of", |env, args| {
-            let item = args.get(0)?.eval(env)?;
-            Ok(item.type_string().as_obj())
+        self.register("type-of", |item: Obj| {
+            item.type_string()
         });
 
         // (quote item)
@@ -231,7 +242,7 @@ impl Env {
                 .eval(env)
         });
 
-        // (assert cond)
+        // (assert cond &optional message)
         self.add_bridge("assert", |env, args| {
             let cond = args
                 .get(0)?
@@ -239,48 +250,50 @@ impl Env {
                 .eq(&Obj::Bool(true))?;
 
             if cond {
-                Ok(Obj::Bool(true))
-            } else {
-                Err(RuntimeAssert)
-            }
+                return Ok(Obj::Bool(true))
+            } 
+
+            let msg = match args.get(1) {
+                Ok(msg) => msg.eval(env)?.as_string(env),
+                Err(_) => args.get(0)?.as_string(env)
+            };
+
+            Err(RuntimeAssert(msg))
         });
 
         // (assert-eq lhs rhs)
         self.add_bridge("assert-eq", |env, args| {
-            let cond = args
-                .get(0)?
-                .eval(env)?
-                .eq(&args.get(1)?.eval(env)?)?;
+            let lhs = args.get(0)?.eval(env)?;
+            let rhs = args.get(1)?.eval(env)?;
 
-            if cond {
+            if lhs.eq(&rhs)? {
                 Ok(Obj::Bool(true))
             } else {
-                Err(RuntimeAssert)
+                let msg = format!("{} != {}", lhs.display(env), rhs.display(env));
+                Err(RuntimeAssert(msg))
             }
         });
 
         // (if cond then else)
-        self.add_bridge("if", |env, args| {
+        self.add_tail_bridge("if", |env, args| {
             let cond = *args
                 .get(0)?
                 .eval(env)?
                 .is_bool()?;
 
             if cond {
-                args
-                    .get(1)?
-                    .eval(env)
+                Ok(Tail::Eval(args.get(1)?.clone()))
             } 
             else {
-                match args.get_cell(2) {
-                    Ok(not) => not.as_ref().eval(env),
-                    Err(_) => Ok(Obj::Nil(()))
+                match args.get(2) {
+                    Ok(not) => Ok(Tail::Eval(not.clone())),
+                    Err(_) => Ok(Tail::Ret(Obj::Nil(())))
                 }
             }
         });
 
         // (when cond ..then)
-        self.add_bridge("when", |env, args| {
+        self.add_tail_bridge("when", |env, args| {
             let cond = *args
                 .get(0)?
                 .eval(env)?
@@ -289,15 +302,15 @@ impl Env {
             if cond {
                 args
                     .shift()
-                    .progn(|obj| obj.as_ref().eval(env))
+                    .progn_tail(env)
             } 
             else {
-                Ok(Obj::Nil(()))
+                Ok(Tail::Ret(Obj::Nil(())))
             }
         });
         
         // (unles cond ..then)
-        self.add_bridge("unless", |env, args| {
+        self.add_tail_bridge("unless", |env, args| {
             let cond = *args
                 .get(0)?
                 .eval(env)?
@@ -306,10 +319,10 @@ impl Env {
             if !cond {
                 args
                     .shift()
-                    .progn(|obj| obj.as_ref().eval(env))
+                    .progn_tail(env)
             } 
             else {
-                Ok(Obj::Nil(()))
+                Ok(Tail::Ret(Obj::Nil(())))
             }
         });
 
@@ -318,12 +331,12 @@ impl Env {
             let mut node = Node::from(vec![args.get_cell(0)?.clone()]);
 
             for item in args.skip(1) {
-                let obj = item
-                    .as_ref()
-                    .val();
+                let obj = env
+                    .resolve(item)
+                    .clone_inner();
 
                 match &obj {
-                    Obj::Lst(lst) => {
+                    Obj::Lst(lst) | Obj::Vct(lst) => {
                         for elem in lst.iter() {
                             node.push(elem.clone());
                         }
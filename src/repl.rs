@@ -0,0 +1,418 @@
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    time::{Duration, Instant}
+};
+
+use jester::{Env, Err, ErrObj, ErrType, Obj};
+
+use crate::editor::Editor;
+
+/// String introduction for `REPL` mode
+const REPL_HEADER: &str = "Welcome to Jester Script, the Rust-Lisp Scripting Langauge!\nDeveloped by Mattan Mohel, 2021-2023";
+
+/// Commands of the REPL, as listed by `:help`
+const REPL_HELP: &str = "\
+:help          lists these commands
+:load file     evaluates a script, found in the search paths
+:reload        evaluates the scripts loaded so far again
+:env           lists the symbols defined in the session
+:type expr     prints the type of what expr evaluates to
+:expand form   prints form with its macros expanded
+:doc sym       describes what sym holds
+:save file     writes the definitions of the session to file
+:reset         starts a new session, dropping every definition
+:time          prints how long the last input took to evaluate
+:error         prints the last error, with its location and call stack
+:quit          ends the session
+
+*1, *2 and *3 hold the last three results, and *e the last error";
+
+/// Prompt of the first line of an input
+const PROMPT: &str = ">> ";
+/// Prompt of the lines continuing an input with open brackets
+const MORE: &str = ".. ";
+
+/// Symbols holding the last results, latest first
+const RESULTS: [&str; 3] = ["*1", "*2", "*3"];
+/// Symbol holding the last error
+const LAST_ERR: &str = "*e";
+
+/// Forms whose inputs are kept by `:save`
+const DEFINERS: [&str; 9] = ["set", "defun", "defun*", "defmacro", "defmacro*", "module", "import", "require", "load"];
+
+/// What the REPL does after an input
+enum Flow {
+    Next,
+    Quit
+}
+
+/// An `Env` and what the REPL knows of its session
+struct Session<F> {
+    build: F,
+    env: Env,
+    /// symbols defined before the session began
+    base: HashSet<String>,
+    /// scripts loaded by `:load`, in order, with the modules they defined
+    loaded: Vec<(PathBuf, Vec<String>)>,
+    /// inputs defining something, kept by `:save`
+    defs: Vec<String>,
+    last: Option<ErrObj>,
+    time: Duration
+}
+
+/// Runs the Read-Eval-Print-Loop on the `Env` made by `build`
+/// until `:quit` or the end of input, first running `start`
+/// as if each were typed in
+///
+/// ## Note
+/// An input that fails to parse or evaluate reports its
+/// error and leaves the `Env` as it was after the last form
+/// that ran, keeping the error in `*e`. `:reset` calls
+/// `build` again for a new `Env`
+pub fn repl<F>(build: F, start: &[String]) -> Err
+where
+    F: Fn() -> Err<Env>
+{
+    println!("{}", REPL_HEADER);
+
+    let mut editor = Editor::new(Editor::default_history());
+    let mut session = Session::new(build)?;
+
+    for input in start {
+        if let Flow::Quit = session.input(input)? {
+            return Ok(())
+        }
+    }
+
+    while let Some(input) = editor.read(PROMPT, MORE, || session.env.sym_names())? {
+        if let Flow::Quit = session.input(&input)? {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+impl<F> Session<F>
+where
+    F: Fn() -> Err<Env>
+{
+    fn new(build: F) -> Err<Self> {
+        let env = build()?;
+
+        Ok(Self {
+            base: env.sym_names().into_iter().collect(),
+            env,
+            build,
+            loaded: Vec::new(),
+            defs: Vec::new(),
+            last: None,
+            time: Duration::new(0, 0)
+        })
+    }
+
+    /// Replaces the `Env` with a new one, forgetting the session
+    fn reset(&mut self) -> Err {
+        self.env = (self.build)()?;
+        self.base = self.env.sym_names().into_iter().collect();
+        self.loaded.clear();
+        self.defs.clear();
+        self.last = None;
+
+        Ok(())
+    }
+
+    /// Runs `input`, a command or forms to evaluate,
+    /// reporting any error it fails with
+    fn input(&mut self, input: &str) -> Err<Flow> {
+        let input = input.trim();
+
+        if input.is_empty() {
+            return Ok(Flow::Next)
+        }
+
+        let res = match input.strip_prefix(':') {
+            Some(cmd) => self.command(cmd),
+            None => self.eval(input)
+        };
+
+        match res {
+            Err(err) => self.fail(err).map(|_| Flow::Next),
+            flow => flow
+        }
+    }
+
+    /// Evaluates `input`, printing and keeping its result
+    fn eval(&mut self, input: &str) -> Err<Flow> {
+        let start = Instant::now();
+        let res = self.env.eval_str(input);
+        self.time = start.elapsed();
+
+        let obj = res?;
+
+        if defines(input) {
+            self.defs.push(input.to_string());
+        }
+
+        self.print(obj)
+    }
+
+    /// Prints `obj`, keeping it in `*1`
+    fn print(&self, obj: Obj) -> Err<Flow> {
+        println!("{}", obj.display(&self.env));
+
+        for i in (1..RESULTS.len()).rev() {
+            let prev = self.env
+                .get(RESULTS[i - 1])
+                .unwrap_or(Obj::Nil(()));
+
+            self.env.set(RESULTS[i], prev)?;
+        }
+
+        self.env.set(RESULTS[0], obj)?;
+        Ok(Flow::Next)
+    }
+
+    /// Reports `err`, keeping it in `*e`
+    fn fail(&mut self, err: ErrType) -> Err {
+        eprintln!("error: {}", err);
+
+        let obj = err.into_obj();
+        self.env.set(LAST_ERR, Obj::Error(obj.clone()))?;
+        self.last = Some(obj);
+
+        Ok(())
+    }
+
+    /// Runs meta-command `cmd`, given without its `:`
+    fn command(&mut self, cmd: &str) -> Err<Flow> {
+        let (name, arg) = match cmd.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (cmd, "")
+        };
+
+        let env = &self.env;
+
+        match (name, arg) {
+            ("help", _) => println!("{}", REPL_HELP),
+
+            ("quit", _) => {
+                println!("quitting...");
+                return Ok(Flow::Quit)
+            }
+
+            ("time", _) => println!("completed in: {:?}...", self.time),
+
+            ("error", _) => match &self.last {
+                Some(err) => report(err),
+                None => println!("no errors yet...")
+            },
+
+            ("load", "") | ("type", "") | ("expand", "") | ("doc", "") | ("save", "") => {
+                println!("usage: :{} {}", name, REPL_HELP
+                    .lines()
+                    .find_map(|line| line.strip_prefix(&format!(":{} ", name)))
+                    .and_then(|usage| usage.split_whitespace().next())
+                    .unwrap_or_default());
+            }
+
+            ("load", file) => {
+                let path = env
+                    .find_script(file)
+                    .unwrap_or_else(|| PathBuf::from(file));
+
+                let (obj, modules) = defining_modules(env, || env.load_file(file))?;
+
+                self.defs.push(format!("(load \"{}\")", path.display()));
+                self.loaded.push((path, modules));
+
+                return self.print(obj)
+            }
+
+            ("reload", _) => {
+                if self.loaded.is_empty() {
+                    println!("no scripts loaded yet...");
+                }
+
+                for (path, modules) in self.loaded.iter_mut() {
+                    // dropped so the script can define them again
+                    for name in modules.iter() {
+                        env.remove_module(name);
+                    }
+
+                    *modules = defining_modules(env, || env.eval_file(&*path))?.1;
+                    println!("reloaded {}", path.display());
+                }
+            }
+
+            ("env", _) => {
+                let names: Vec<String> = env
+                    .sym_names()
+                    .into_iter()
+                    .filter(|name| !self.base.contains(name))
+                    .filter(|name| !RESULTS.contains(&name.as_str()) && name != LAST_ERR)
+                    .collect();
+
+                if names.is_empty() {
+                    println!("nothing defined yet...");
+                }
+
+                for name in names {
+                    let obj = env.get(&name).unwrap_or(Obj::Nil(()));
+                    println!("{} : {}", name, obj.type_string());
+                }
+            }
+
+            ("type", expr) => {
+                let obj = env.eval_str(expr)?;
+                println!("{}", obj.type_string());
+            }
+
+            ("expand", form) => {
+                let form = env.eval_str(&format!("(quote {})", form))?;
+                println!("{}", env.expand(&form)?.display(env));
+            }
+
+            ("doc", sym) => match env.get(sym) {
+                Some(obj) => describe(env, sym, &obj),
+                None => println!("'{}' is not defined", sym)
+            },
+
+            ("save", file) => {
+                let src = self.defs.join("\n\n") + "\n";
+                std::fs::write(file, src)?;
+                println!("saved {} definition(s) to {}", self.defs.len(), file);
+            }
+
+            ("reset", _) => {
+                self.reset()?;
+                println!("session reset...");
+            }
+
+            _ => println!("unknown command ':{}', see :help", name)
+        }
+
+        Ok(Flow::Next)
+    }
+}
+
+/// Runs `load`, returning its value and the modules it defined
+fn defining_modules<L>(env: &Env, load: L) -> Err<(Obj, Vec<String>)>
+where
+    L: FnOnce() -> Err<Obj>
+{
+    let before = env.module_names();
+    let obj = load()?;
+
+    let modules = env
+        .module_names()
+        .into_iter()
+        .filter(|name| !before.contains(name))
+        .collect();
+
+    Ok((obj, modules))
+}
+
+/// Returns whether `input` begins with a form defining something
+fn defines(input: &str) -> bool {
+    let head = input
+        .strip_prefix('(')
+        .and_then(|form| form.split(|ch: char| ch.is_whitespace() || ch == '(' || ch == ')').next());
+
+    matches!(head, Some(head) if DEFINERS.contains(&head))
+}
+
+/// Prints the documentation of `obj`, held by `sym`
+fn describe(env: &Env, sym: &str, obj: &Obj) {
+    let show = |params: &jester::Node| Obj::Lst(params.clone()).display(env);
+
+    let (sig, doc) = match obj {
+        Obj::Native(f) => (format!("function ({} {})", sym, show(f.params()).trim_matches(['(', ')'])), f.doc()),
+        Obj::Macro(f) => (format!("macro ({} {})", sym, show(f.params()).trim_matches(['(', ')'])), f.doc()),
+        Obj::Bridge(f) if f.is_denied() => (format!("bridge {}, not granted", sym), None),
+        Obj::Bridge(_) => (format!("bridge {}, defined by the host", sym), None),
+        obj => (format!("{} : {} = {}", sym, obj.type_string(), obj.display(env)), None)
+    };
+
+    println!("{}", sig);
+
+    if let Some(doc) = doc {
+        println!("  {}", doc);
+    }
+}
+
+/// Prints `err` in full, with its location and call stack
+fn report(err: &ErrObj) {
+    println!("{} error: {}", err.kind(), err.message());
+
+    if let Some(span) = err.span() {
+        println!("  --> {}", span);
+    }
+
+    if let Some(trace) = err.trace() {
+        println!("  {}", trace);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs each of `inputs` in a new session, returning it
+    fn session(inputs: &[&str]) -> Session<fn() -> Err<Env>> {
+        let mut session = Session::new(Env::new as fn() -> Err<Env>).unwrap();
+
+        for input in inputs {
+            assert!(matches!(session.input(input), Ok(Flow::Next)), "{}", input);
+        }
+
+        session
+    }
+
+    /// Returns what `sym` holds in `session`, as printed
+    fn get(session: &Session<fn() -> Err<Env>>, sym: &str) -> String {
+        let obj = session.env
+            .get(sym)
+            .unwrap_or(Obj::Nil(()));
+
+        obj.display(&session.env)
+    }
+
+    #[test]
+    fn errors_keep_the_session() {
+        let session = session(&["(set x 1) (/ x 0) (set y 2)", "(+ x 1)"]);
+
+        assert_eq!(get(&session, "x"), "1");
+        assert_eq!(get(&session, "y"), "nil");
+        assert_eq!(get(&session, "*1"), "2");
+        assert_eq!(session.last.as_ref().unwrap().kind(), "div-by-zero");
+    }
+
+    #[test]
+    fn errors_are_kept_in_last_err() {
+        let unbalanced = session(&["(+ 1", "'(a ,b"]);
+        assert_eq!(unbalanced.last.as_ref().unwrap().kind(), "unbalanced");
+
+        let thrown = session(&["(throw 42)", "(error-payload *e)"]);
+        assert_eq!(get(&thrown, "*1"), "42");
+    }
+
+    #[test]
+    fn results_shift_through_history() {
+        let session = session(&["1", "2", "3", "4"]);
+
+        assert_eq!(get(&session, "*1"), "4");
+        assert_eq!(get(&session, "*2"), "3");
+        assert_eq!(get(&session, "*3"), "2");
+    }
+
+    #[test]
+    fn failing_commands_keep_the_session() {
+        let mut session = session(&["(set x 5)", ":load no-such-script", ":type (car", ":nope"]);
+        assert_eq!(get(&session, "x"), "5");
+        assert_eq!(session.last.as_ref().unwrap().kind(), "unbalanced");
+
+        assert!(matches!(session.input(":quit"), Ok(Flow::Quit)));
+    }
+}
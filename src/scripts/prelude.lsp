@@ -3,8 +3,7 @@
 
 (defmacro* for (it in min to max body)
 	(let (res (gen-sym))
-		'(do
-			(set ,it ,min)
+		'(let (,it ,min ,res ())
 			(loop (< ,it ,max)
 				(set ,res (apply do ,body))
 				(incr ,it 1)
@@ -12,8 +11,7 @@
 
 (defmacro* for-each (var in list body)
 	(let (it  (gen-sym))
-		'(do
-			(set ,it 0)
+		'(let (,it 0 ,var ())
 			(loop (< ,it (len ,list))
 				(set ,var (nth ,it ,list))
 				(incr ,it 1)
@@ -0,0 +1,78 @@
+use std::{
+    fs,
+    process::{Command, Output}
+};
+
+/// Runs the `jester` binary with `args`
+fn jester(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_jester"))
+        .args(args)
+        .output()
+        .expect("couldn't run jester")
+}
+
+fn stdout(out: &Output) -> String {
+    String::from_utf8_lossy(&out.stdout).into_owned()
+}
+
+fn stderr(out: &Output) -> String {
+    String::from_utf8_lossy(&out.stderr).into_owned()
+}
+
+#[test]
+fn exprs_print_their_values() {
+    let out = jester(&["-e", "(+ 1 2)", "-e", "(len *args*)", "a", "b"]);
+
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(stdout(&out), "3\n2\n");
+}
+
+#[test]
+fn scripts_get_their_args() {
+    let script = std::env::temp_dir().join(format!("jester-cli-{}.lsp", std::process::id()));
+    fs::write(&script, "#!/usr/bin/env jester\n(println (nth 1 *args*))").unwrap();
+
+    let out = jester(&[script.to_str().unwrap(), "first", "second"]);
+    fs::remove_file(&script).unwrap();
+
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(stdout(&out), "second\n");
+}
+
+#[test]
+fn errors_exit_with_1() {
+    let out = jester(&["-e", "(/ 1 0)"]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(stderr(&out).starts_with("error: division by zero"));
+
+    let out = jester(&["no-such-script.lsp"]);
+    assert_eq!(out.status.code(), Some(1));
+
+    // runaway recursion fails instead of overflowing the stack
+    let out = jester(&["-e", "(defun f (n) (+ 1 (f n))) (f 1)"]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(stderr(&out).contains("deeper than"));
+}
+
+#[test]
+fn bad_options_exit_with_2() {
+    let out = jester(&["--nope"]);
+    assert_eq!(out.status.code(), Some(2));
+    assert!(stderr(&out).contains("unknown option '--nope'"));
+
+    let out = jester(&["-e"]);
+    assert_eq!(out.status.code(), Some(2));
+
+    let out = jester(&["--help"]);
+    assert_eq!(out.status.code(), Some(0));
+    assert!(stdout(&out).starts_with("usage: jester"));
+}
+
+#[test]
+fn no_prelude_leaves_out_its_macros() {
+    let out = jester(&["--no-prelude", "-e", "(range 0 to 3)"]);
+    assert_eq!(out.status.code(), Some(1));
+
+    let out = jester(&["-e", "(range 0 to 3)"]);
+    assert_eq!(stdout(&out), "(0 1 2)\n");
+}